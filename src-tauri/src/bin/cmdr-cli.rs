@@ -0,0 +1,3 @@
+fn main() {
+    cmdr_lib::cli::run()
+}
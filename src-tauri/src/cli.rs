@@ -0,0 +1,324 @@
+use crate::config::{self, Command, Project, Workspace};
+use crate::error::{CmdrError, Result};
+use crate::terminal::{self, ExistingSession};
+use clap::{Parser, Subcommand};
+
+/// Launch cmdr workspaces and commands from the shell
+#[derive(Debug, Parser)]
+#[command(name = "cmdr-cli", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliCommand {
+    /// Inspect configured projects
+    Projects {
+        #[command(subcommand)]
+        command: ProjectsCommand,
+    },
+    /// Launch a workspace in the configured terminal
+    Launch {
+        /// Project name or id
+        project: String,
+        /// Workspace name or id
+        workspace: String,
+        /// Environment to launch with instead of the workspace default
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Print the launch plan as JSON instead of launching
+        #[arg(long)]
+        plan: bool,
+        /// Attach to the workspace's session if it is already running
+        #[arg(long, conflicts_with = "recreate")]
+        attach: bool,
+        /// End the workspace's running session and launch it again
+        #[arg(long)]
+        recreate: bool,
+    },
+    /// Stop a launched workspace and the programs running in it
+    Stop {
+        /// Project name or id
+        project: String,
+        /// Workspace name or id
+        workspace: String,
+    },
+    /// Run one of the project's saved commands, or a global command
+    Run {
+        /// Project name or id
+        project: String,
+        /// Command name or id
+        command: String,
+        /// Environment to run the command with
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Value of a command parameter or input, as NAME=VALUE
+        #[arg(short, long = "param", value_name = "NAME=VALUE", value_parser = parse_param)]
+        params: Vec<(String, String)>,
+    },
+    /// Print a project environment as shell exports, for
+    /// `eval "$(cmdr-cli env <project> <env>)"`
+    Env {
+        /// Project name or id
+        project: String,
+        /// Environment name
+        environment: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ProjectsCommand {
+    /// List projects, most recently opened first
+    List {
+        /// Print the projects as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Entry point of the `cmdr-cli` binary
+pub fn run() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"))
+        .format_timestamp_millis()
+        .init();
+
+    let cli = Cli::parse();
+    if let Err(e) = execute(cli.command) {
+        eprintln!("cmdr: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn execute(command: CliCommand) -> Result<()> {
+    match command {
+        CliCommand::Projects {
+            command: ProjectsCommand::List { json },
+        } => {
+            let projects = config::load_projects()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&projects)?);
+                return Ok(());
+            }
+            for project in projects {
+                let workspaces: Vec<&str> =
+                    project.workspaces.iter().map(|w| w.name.as_str()).collect();
+                println!(
+                    "{}\t{}\t{}",
+                    project.name,
+                    project.path,
+                    workspaces.join(", ")
+                );
+            }
+        }
+        CliCommand::Launch {
+            project,
+            workspace,
+            env,
+            plan,
+            attach,
+            recreate,
+        } => {
+            let project = find_project(config::load_projects()?, &project)?;
+            let workspace = find_workspace(&project, &workspace)?;
+
+            if plan {
+                let plan = terminal::preview_launch(project.id.clone(), workspace.id.clone(), env)?;
+                println!("{}", serde_json::to_string_pretty(&plan)?);
+            } else {
+                let existing = if attach {
+                    Some(ExistingSession::Attach)
+                } else if recreate {
+                    Some(ExistingSession::Recreate)
+                } else {
+                    None
+                };
+                terminal::launch_workspace(
+                    project.id.clone(),
+                    workspace.id.clone(),
+                    env,
+                    existing,
+                )?;
+            }
+        }
+        CliCommand::Stop { project, workspace } => {
+            let project = find_project(config::load_projects()?, &project)?;
+            let workspace = find_workspace(&project, &workspace)?;
+            terminal::stop_workspace(project.id.clone(), workspace.id.clone())?;
+        }
+        CliCommand::Run {
+            project,
+            command,
+            env,
+            params,
+        } => {
+            let project = find_project(config::load_projects()?, &project)?;
+            let settings = config::load_settings()?;
+            let command = find_command(&project.commands, &command)
+                .or_else(|| find_command(&settings.global_commands, &command))
+                .ok_or_else(|| CmdrError::ProjectNotFound(format!("command:{}", command)))?;
+
+            terminal::run_command(
+                project.id.clone(),
+                command.command.clone(),
+                env,
+                Some(command.id.clone()),
+                Some(params.into_iter().collect()),
+            )?;
+        }
+        CliCommand::Env {
+            project,
+            environment,
+        } => {
+            let project = find_project(config::load_projects()?, &project)?;
+            let environment = project
+                .environment(&environment)
+                .ok_or(CmdrError::EnvironmentNotFound(environment))?;
+
+            if let Some(line) = terminal::environment_exports(environment) {
+                println!("{}", line);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Find a project by id, or by name ignoring case
+fn find_project(mut projects: Vec<Project>, query: &str) -> Result<Project> {
+    let index = projects
+        .iter()
+        .position(|p| p.id == query)
+        .or_else(|| {
+            projects
+                .iter()
+                .position(|p| p.name.eq_ignore_ascii_case(query))
+        })
+        .ok_or_else(|| CmdrError::ProjectNotFound(query.to_string()))?;
+    Ok(projects.swap_remove(index))
+}
+
+fn find_workspace<'a>(project: &'a Project, query: &str) -> Result<&'a Workspace> {
+    project
+        .workspaces
+        .iter()
+        .find(|w| w.id == query)
+        .or_else(|| {
+            project
+                .workspaces
+                .iter()
+                .find(|w| w.name.eq_ignore_ascii_case(query))
+        })
+        .ok_or_else(|| CmdrError::ProjectNotFound(format!("workspace:{}", query)))
+}
+
+fn parse_param(arg: &str) -> std::result::Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got {}", arg))
+}
+
+fn find_command<'a>(commands: &'a [Command], query: &str) -> Option<&'a Command> {
+    commands
+        .iter()
+        .find(|c| c.id == query)
+        .or_else(|| commands.iter().find(|c| c.name.eq_ignore_ascii_case(query)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures;
+
+    fn project(id: &str, name: &str) -> Project {
+        Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            commands: vec![Command {
+                name: "Dev Server".to_string(),
+                ..fixtures::command("c1", "npm run dev")
+            }],
+            ..fixtures::project("/work")
+        }
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let cli = Cli::try_parse_from([
+            "cmdr-cli", "launch", "app", "dev", "--env", "staging", "--plan",
+        ])
+        .unwrap();
+        match cli.command {
+            CliCommand::Launch {
+                project,
+                workspace,
+                env,
+                plan,
+                ..
+            } => {
+                assert_eq!((project.as_str(), workspace.as_str()), ("app", "dev"));
+                assert_eq!(env.as_deref(), Some("staging"));
+                assert!(plan);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        let cli = Cli::try_parse_from(["cmdr-cli", "projects", "list"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            CliCommand::Projects {
+                command: ProjectsCommand::List { json: false }
+            }
+        ));
+        assert!(Cli::try_parse_from(["cmdr-cli", "run", "app"]).is_err());
+        let cli = Cli::try_parse_from([
+            "cmdr-cli",
+            "run",
+            "app",
+            "migrate",
+            "-p",
+            "name=add users",
+            "--param",
+            "steps=2",
+        ])
+        .unwrap();
+        match cli.command {
+            CliCommand::Run { params, .. } => assert_eq!(
+                params,
+                vec![
+                    ("name".to_string(), "add users".to_string()),
+                    ("steps".to_string(), "2".to_string())
+                ]
+            ),
+            other => panic!("unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["cmdr-cli", "run", "app", "migrate", "-p", "name"]).is_err());
+        assert!(Cli::try_parse_from([
+            "cmdr-cli",
+            "launch",
+            "app",
+            "dev",
+            "--attach",
+            "--recreate"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_find_by_id_or_name() {
+        let projects = vec![project("p1", "Web App"), project("p2", "p1")];
+
+        // Ids take precedence over names
+        assert_eq!(find_project(projects.clone(), "p1").unwrap().id, "p1");
+        assert_eq!(find_project(projects.clone(), "web app").unwrap().id, "p1");
+        assert!(find_project(projects.clone(), "api").is_err());
+
+        let commands = &projects[0].commands;
+        assert_eq!(
+            find_command(commands, "dev server").unwrap().command,
+            "npm run dev"
+        );
+        assert_eq!(find_command(commands, "c1").unwrap().command, "npm run dev");
+        assert!(find_command(commands, "build").is_none());
+    }
+}
@@ -1,5 +1,8 @@
-use crate::config::{self, NewProject, Project, Environment, Workspace, Command};
+use crate::config::{
+    self, Command, Environment, NewProject, Project, SyncReport, ValidationIssue, Workspace,
+};
 use crate::error::{CmdrError, Result};
+use serde::Serialize;
 use std::collections::HashMap;
 
 #[tauri::command]
@@ -14,6 +17,7 @@ pub fn get_projects() -> Result<Vec<Project>> {
 pub fn add_project(project: NewProject) -> Result<Project> {
     log::info!("[Projects] Adding new project: {}", project.name);
     let project = project.into_project();
+    ensure_valid(&project, None)?;
     config::save_project(&project)?;
     log::info!("[Projects] Project added with ID: {}", project.id);
     Ok(project)
@@ -22,16 +26,17 @@ pub fn add_project(project: NewProject) -> Result<Project> {
 #[tauri::command]
 pub fn update_project(id: String, updates: HashMap<String, serde_json::Value>) -> Result<Project> {
     log::info!("[Projects] Updating project: {}", id);
-    log::debug!("[Projects] Update fields: {:?}", updates.keys().collect::<Vec<_>>());
+    log::debug!(
+        "[Projects] Update fields: {:?}",
+        updates.keys().collect::<Vec<_>>()
+    );
 
     let projects = config::load_projects()?;
-    let mut project = projects
-        .into_iter()
-        .find(|p| p.id == id)
-        .ok_or_else(|| {
-            log::error!("[Projects] Project not found: {}", id);
-            CmdrError::ProjectNotFound(id.clone())
-        })?;
+    let mut project = projects.into_iter().find(|p| p.id == id).ok_or_else(|| {
+        log::error!("[Projects] Project not found: {}", id);
+        CmdrError::ProjectNotFound(id.clone())
+    })?;
+    let stored = project.clone();
 
     // Apply updates
     if let Some(name) = updates.get("name").and_then(|v| v.as_str()) {
@@ -61,29 +66,109 @@ pub fn update_project(id: String, updates: HashMap<String, serde_json::Value>) -
         log::debug!("[Projects] Updating environments");
         match serde_json::from_value::<HashMap<String, Environment>>(environments.clone()) {
             Ok(envs) => project.environments = envs,
-            Err(e) => log::error!("[Projects] Failed to parse environments: {} - value: {:?}", e, environments),
+            Err(e) => log::error!(
+                "[Projects] Failed to parse environments: {} - value: {:?}",
+                e,
+                environments
+            ),
         }
     }
     if let Some(workspaces) = updates.get("workspaces") {
         log::debug!("[Projects] Updating workspaces");
         match serde_json::from_value::<Vec<Workspace>>(workspaces.clone()) {
             Ok(ws) => project.workspaces = ws,
-            Err(e) => log::error!("[Projects] Failed to parse workspaces: {} - value: {:?}", e, workspaces),
+            Err(e) => log::error!(
+                "[Projects] Failed to parse workspaces: {} - value: {:?}",
+                e,
+                workspaces
+            ),
         }
     }
     if let Some(commands) = updates.get("commands") {
         log::debug!("[Projects] Updating commands");
         match serde_json::from_value::<Vec<Command>>(commands.clone()) {
             Ok(cmds) => project.commands = cmds,
-            Err(e) => log::error!("[Projects] Failed to parse commands: {} - value: {:?}", e, commands),
+            Err(e) => log::error!(
+                "[Projects] Failed to parse commands: {} - value: {:?}",
+                e,
+                commands
+            ),
         }
     }
 
+    ensure_valid(&project, Some(&stored))?;
     config::save_project(&project)?;
     log::info!("[Projects] Project updated successfully: {}", project.name);
     Ok(project)
 }
 
+/// Check a workspace without saving it, so the editor can point at the
+/// offending panes
+#[tauri::command]
+pub fn validate_workspace(workspace: Workspace) -> Vec<ValidationIssue> {
+    log::debug!("[Projects] Validating workspace: {}", workspace.name);
+    config::validate_workspace(&workspace)
+}
+
+/// A project after syncing its imported commands, with what changed
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandSync {
+    pub project: Project,
+    pub report: SyncReport,
+}
+
+/// Propose commands from the task runner files in a directory, for a project
+/// that is being added
+#[tauri::command]
+pub fn discover_commands(path: String) -> Vec<Command> {
+    log::info!("[Projects] Discovering commands in: {}", path);
+    config::discover_commands(std::path::Path::new(&path))
+}
+
+/// Re-import a project's commands from its repository, leaving commands
+/// that were edited since they were imported alone
+#[tauri::command]
+pub fn sync_project_commands(id: String) -> Result<CommandSync> {
+    log::info!("[Projects] Syncing imported commands of project: {}", id);
+
+    let projects = config::load_projects()?;
+    let mut project = projects.into_iter().find(|p| p.id == id).ok_or_else(|| {
+        log::error!("[Projects] Project not found: {}", id);
+        CmdrError::ProjectNotFound(id.clone())
+    })?;
+
+    let discovered = config::discover_commands(std::path::Path::new(&project.path));
+    let report = config::sync_commands(&mut project.commands, discovered);
+    log::info!(
+        "[Projects] Synced commands: {} added, {} updated, {} removed, {} kept",
+        report.added.len(),
+        report.updated.len(),
+        report.removed.len(),
+        report.kept.len()
+    );
+
+    // Only commands change, so there are no workspaces to validate
+    config::save_project(&project)?;
+    Ok(CommandSync { project, report })
+}
+
+/// Refuse to save a project whose new or changed workspaces have invalid
+/// layouts
+fn ensure_valid(project: &Project, stored: Option<&Project>) -> Result<()> {
+    let issues = config::validate_project(project, stored);
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    log::error!(
+        "[Projects] Project {} has {} layout issues",
+        project.name,
+        issues.len()
+    );
+    Err(CmdrError::Validation(issues))
+}
+
 #[tauri::command]
 pub fn delete_project(id: String) -> Result<()> {
     log::info!("[Projects] Deleting project: {}", id);
@@ -97,15 +182,16 @@ pub fn open_project(id: String) -> Result<()> {
     log::info!("[Projects] Opening project: {}", id);
 
     let projects = config::load_projects()?;
-    let mut project = projects
-        .into_iter()
-        .find(|p| p.id == id)
-        .ok_or_else(|| {
-            log::error!("[Projects] Project not found: {}", id);
-            CmdrError::ProjectNotFound(id.clone())
-        })?;
+    let mut project = projects.into_iter().find(|p| p.id == id).ok_or_else(|| {
+        log::error!("[Projects] Project not found: {}", id);
+        CmdrError::ProjectNotFound(id.clone())
+    })?;
 
-    log::info!("[Projects] Found project: {} at path: {}", project.name, project.path);
+    log::info!(
+        "[Projects] Found project: {} at path: {}",
+        project.name,
+        project.path
+    );
 
     // Update last opened timestamp
     project.last_opened = Some(chrono::Utc::now().to_rfc3339());
@@ -118,7 +204,8 @@ pub fn open_project(id: String) -> Result<()> {
         log::info!("[Projects] Opening folder in Finder: {}", project.path);
         match std::process::Command::new("open")
             .arg(&project.path)
-            .spawn() {
+            .spawn()
+        {
             Ok(_) => log::info!("[Projects] Folder opened successfully"),
             Err(e) => log::error!("[Projects] Failed to open folder: {}", e),
         }
@@ -129,7 +216,8 @@ pub fn open_project(id: String) -> Result<()> {
         log::info!("[Projects] Opening folder in Explorer: {}", project.path);
         match std::process::Command::new("explorer")
             .arg(&project.path)
-            .spawn() {
+            .spawn()
+        {
             Ok(_) => log::info!("[Projects] Folder opened successfully"),
             Err(e) => log::error!("[Projects] Failed to open folder: {}", e),
         }
@@ -140,7 +228,8 @@ pub fn open_project(id: String) -> Result<()> {
         log::info!("[Projects] Opening folder with xdg-open: {}", project.path);
         match std::process::Command::new("xdg-open")
             .arg(&project.path)
-            .spawn() {
+            .spawn()
+        {
             Ok(_) => log::info!("[Projects] Folder opened successfully"),
             Err(e) => log::error!("[Projects] Failed to open folder: {}", e),
         }
@@ -0,0 +1,751 @@
+use super::{
+    Command, CommandParameter, CommandSource, ConfigLayer, ImportedCommand, ParameterType,
+    SourceKind,
+};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+const PROCFILE: &str = "Procfile";
+const PACKAGE_JSON: &str = "package.json";
+/// In the order make looks for them
+const MAKEFILES: [&str; 3] = ["GNUmakefile", "makefile", "Makefile"];
+const JUSTFILES: [&str; 3] = ["justfile", "Justfile", ".justfile"];
+const CARGO_CONFIGS: [&str; 2] = [".cargo/config.toml", ".cargo/config"];
+
+/// npm scripts run by the package manager on its own
+const LIFECYCLE_SCRIPTS: [&str; 8] = [
+    "install",
+    "preinstall",
+    "postinstall",
+    "prepare",
+    "prepublish",
+    "prepublishOnly",
+    "prepack",
+    "postpack",
+];
+
+/// What syncing a project's imported commands changed, by command name
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Imported commands that were edited since, left as they are
+    pub kept: Vec<String>,
+}
+
+/// Commands found in the task runner files at the root of a project:
+/// Procfile processes, package.json scripts, Makefile targets, justfile
+/// recipes and cargo aliases. Files that cannot be read or parsed are
+/// skipped.
+pub fn discover_commands(path: &Path) -> Vec<Command> {
+    let read = |name: &str| {
+        let file = path.join(name);
+        if !file.is_file() {
+            return None;
+        }
+        std::fs::read_to_string(&file)
+            .map_err(|e| log::warn!("[Config] Failed to read {:?}: {}", file, e))
+            .ok()
+    };
+
+    let mut commands = Vec::new();
+    if let Some(content) = read(PROCFILE) {
+        commands.extend(procfile(&content));
+    }
+    if let Some(content) = read(PACKAGE_JSON) {
+        match package_json(&content, package_manager(path)) {
+            Ok(scripts) => commands.extend(scripts),
+            Err(e) => log::warn!("[Config] Failed to parse package.json: {}", e),
+        }
+    }
+    if let Some(content) = MAKEFILES.iter().find_map(|name| read(name)) {
+        commands.extend(makefile(&content));
+    }
+    if let Some(content) = JUSTFILES.iter().find_map(|name| read(name)) {
+        commands.extend(justfile(&content));
+    }
+    if let Some(content) = CARGO_CONFIGS.iter().find_map(|name| read(name)) {
+        match cargo_aliases(&content) {
+            Ok(aliases) => commands.extend(aliases),
+            Err(e) => log::warn!("[Config] Failed to parse cargo config: {}", e),
+        }
+    }
+
+    log::info!("[Config] Found {} commands in {:?}", commands.len(), path);
+    commands
+}
+
+/// Bring a project's imported commands in line with what was discovered.
+/// New ones are added, changed ones updated and ones that are gone removed,
+/// except for imported commands that were edited since.
+pub fn sync_commands(commands: &mut Vec<Command>, discovered: Vec<Command>) -> SyncReport {
+    let mut report = SyncReport::default();
+    let found = |source: &CommandSource| {
+        discovered
+            .iter()
+            .any(|d| d.source.as_ref().is_some_and(|s| same_source(s, source)))
+    };
+
+    commands.retain(|command| match &command.source {
+        Some(source) if !found(source) => {
+            if is_edited(command) {
+                report.kept.push(command.name.clone());
+                true
+            } else {
+                report.removed.push(command.name.clone());
+                false
+            }
+        }
+        _ => true,
+    });
+
+    for new in discovered {
+        let Some(source) = &new.source else {
+            continue;
+        };
+        let existing = commands
+            .iter_mut()
+            .find(|c| c.source.as_ref().is_some_and(|s| same_source(s, source)));
+        match existing {
+            None => {
+                report.added.push(new.name.clone());
+                commands.push(new);
+            }
+            Some(existing) if is_edited(existing) => report.kept.push(existing.name.clone()),
+            Some(existing) if existing.source != new.source => {
+                report.updated.push(existing.name.clone());
+                existing.name = new.name;
+                existing.command = new.command;
+                existing.description = new.description;
+                existing.parameters = new.parameters;
+                existing.source = new.source;
+            }
+            Some(_) => {}
+        }
+    }
+
+    report
+}
+
+fn same_source(a: &CommandSource, b: &CommandSource) -> bool {
+    a.kind == b.kind && a.name == b.name
+}
+
+/// Whether an imported command was changed by hand since it was imported:
+/// any imported field differs, or something was added to it
+fn is_edited(command: &Command) -> bool {
+    command.source.as_ref().is_some_and(|source| {
+        source.imported != ImportedCommand::of(command)
+            || command.icon.is_some()
+            || !command.depends_on.is_empty()
+            || command.service.is_some()
+    })
+}
+
+fn imported(kind: SourceKind, name: &str, command: String, description: Option<String>) -> Command {
+    imported_with(kind, name, command, description, Vec::new())
+}
+
+fn imported_with(
+    kind: SourceKind,
+    name: &str,
+    command: String,
+    description: Option<String>,
+    parameters: Vec<CommandParameter>,
+) -> Command {
+    let mut command = Command {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        command,
+        description: description.filter(|d| !d.is_empty()),
+        icon: None,
+        parameters,
+        depends_on: Vec::new(),
+        service: None,
+        source: None,
+        layer: ConfigLayer::User,
+    };
+    command.source = Some(CommandSource {
+        kind,
+        name: name.to_string(),
+        imported: ImportedCommand::of(&command),
+    });
+    command
+}
+
+/// Quote a name for the shell unless it is plain
+fn shell_word(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:/@+=".contains(c))
+    {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// `name: command` lines
+fn procfile(content: &str) -> Vec<Command> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, command)| (name.trim(), command.trim()))
+        .filter(|(name, command)| {
+            !name.is_empty()
+                && !command.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(|(name, command)| imported(SourceKind::Procfile, name, command.to_string(), None))
+        .collect()
+}
+
+/// The package manager whose lock file the project has
+fn package_manager(path: &Path) -> &'static str {
+    [
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+        ("bun.lockb", "bun"),
+        ("bun.lock", "bun"),
+    ]
+    .into_iter()
+    .find(|(lock, _)| path.join(lock).exists())
+    .map_or("npm", |(_, manager)| manager)
+}
+
+/// `scripts`, without install hooks and without `pre`/`post` scripts that
+/// run along with another one
+fn package_json(content: &str, manager: &str) -> serde_json::Result<Vec<Command>> {
+    let package: serde_json::Value = serde_json::from_str(content)?;
+    let Some(scripts) = package.get("scripts").and_then(|s| s.as_object()) else {
+        return Ok(Vec::new());
+    };
+
+    let hook = |name: &str| {
+        ["pre", "post"].iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .is_some_and(|base| scripts.contains_key(base))
+        })
+    };
+    Ok(scripts
+        .iter()
+        .filter(|(name, _)| !LIFECYCLE_SCRIPTS.contains(&name.as_str()) && !hook(name))
+        .filter_map(|(name, script)| Some((name, script.as_str()?)))
+        .map(|(name, script)| {
+            let command = format!("{} run {}", manager, shell_word(name));
+            imported(
+                SourceKind::PackageJson,
+                name,
+                command,
+                Some(script.to_string()),
+            )
+        })
+        .collect())
+}
+
+/// Targets that look like tasks rather than files, with the comment above
+/// them or a `## help` text after them as description
+fn makefile(content: &str) -> Vec<Command> {
+    let mut commands = Vec::new();
+    let mut seen = HashSet::new();
+    let mut comment: Option<String> = None;
+
+    for line in content.lines() {
+        if line.starts_with('\t') {
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+
+        let rule = line
+            .split_once(':')
+            .filter(|(targets, rest)| !is_assignment(rest) && !targets.contains(['=', '$', '%']));
+        if let Some((targets, rest)) = rule {
+            let help = rest
+                .split_once("##")
+                .map(|(_, help)| help.trim().to_string());
+            let description = help.or_else(|| comment.clone());
+            let tasks = targets.split_whitespace().filter(|target| {
+                !target.starts_with('.')
+                    && target
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            });
+            for target in tasks {
+                if seen.insert(target.to_string()) {
+                    let command = format!("make {}", target);
+                    commands.push(imported(
+                        SourceKind::Makefile,
+                        target,
+                        command,
+                        description.clone(),
+                    ));
+                }
+            }
+        }
+        comment = None;
+    }
+    commands
+}
+
+/// Whether the part of a Makefile line after its first `:` makes it a
+/// variable assignment (`:=`, `::=`, `:::=`) or a target-specific variable
+/// (`target: VAR=value`) rather than a rule
+fn is_assignment(rest: &str) -> bool {
+    rest.trim_start_matches(':').starts_with('=')
+        || rest
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| word.contains('='))
+}
+
+/// Public recipes, with their parameters as command parameters and the
+/// comment above them as description
+fn justfile(content: &str) -> Vec<Command> {
+    const KEYWORDS: [&str; 6] = ["set", "alias", "export", "import", "mod", "unexport"];
+    let mut commands = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut private = false;
+
+    for line in content.lines() {
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            comment = None;
+            private = false;
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            if !text.starts_with('!') {
+                comment = Some(text.trim().to_string());
+            }
+            continue;
+        }
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+
+        let header = (!line.contains(":=")
+            && !KEYWORDS.contains(&line.split_whitespace().next().unwrap_or_default()))
+        .then(|| recipe_header(line.trim_start_matches('@')))
+        .flatten();
+        if let Some((name, parameters)) = header {
+            if !private && !name.starts_with('_') {
+                let placeholders: String = parameters
+                    .iter()
+                    .map(|p| format!(" {{{{{}}}}}", p.name))
+                    .collect();
+                let command = format!("just {}{}", name, placeholders);
+                commands.push(imported_with(
+                    SourceKind::Justfile,
+                    &name,
+                    command,
+                    comment.clone(),
+                    parameters,
+                ));
+            }
+        }
+        comment = None;
+        private = false;
+    }
+    commands
+}
+
+/// Name and parameters of a `name param="default" +rest: dependencies`
+/// recipe line
+fn recipe_header(line: &str) -> Option<(String, Vec<CommandParameter>)> {
+    let colon = outside_quotes(line, ':')?;
+    let mut words = split_words(&line[..colon]).into_iter();
+    let name = words.next()?;
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return None;
+    }
+
+    let parameters = words
+        .map(|word| {
+            let optional_rest = word.starts_with('*');
+            let word = word.trim_start_matches(['$', '+', '*']);
+            let (name, default) = match word.split_once('=') {
+                Some((name, default)) => (name, Some(default)),
+                None => (word, None),
+            };
+            // Only literal defaults can be shown; just works out the others
+            let literal = default.and_then(|d| {
+                let unquoted = d.trim_matches(|c| c == '"' || c == '\'');
+                (d.len() >= 2 && d.len() == unquoted.len() + 2).then(|| unquoted.to_string())
+            });
+            CommandParameter {
+                name: name.to_string(),
+                kind: ParameterType::String,
+                required: default.is_none() && !optional_rest,
+                default: literal,
+                choices: Vec::new(),
+            }
+        })
+        .collect();
+    Some((name, parameters))
+}
+
+/// Position of the first `target` that is not inside quotes
+fn outside_quotes(line: &str, target: char) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == target => return Some(index),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Split on whitespace that is not inside quotes, keeping the quotes
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let end = outside_quotes(rest, ' ').unwrap_or(rest.len());
+        words.push(rest[..end].to_string());
+        rest = rest[end..].trim_start();
+    }
+    words
+}
+
+/// The `[alias]` table of `.cargo/config.toml`
+fn cargo_aliases(content: &str) -> Result<Vec<Command>, toml::de::Error> {
+    let config: toml::Value = toml::from_str(content)?;
+    let Some(aliases) = config.get("alias").and_then(|a| a.as_table()) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(aliases
+        .iter()
+        .filter_map(|(name, expansion)| {
+            let expansion = match expansion {
+                toml::Value::String(s) => s.clone(),
+                toml::Value::Array(words) => words
+                    .iter()
+                    .filter_map(|w| w.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                _ => return None,
+            };
+            let command = format!("cargo {}", shell_word(name));
+            Some(imported(
+                SourceKind::CargoAlias,
+                name,
+                command,
+                Some(expansion),
+            ))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(commands: &[Command]) -> Vec<(&str, &str, Option<&str>)> {
+        commands
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.command.as_str(),
+                    c.description.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_procfile_and_package_json() {
+        let procfile = procfile(
+            "# processes\nweb: bundle exec rails s -p $PORT\nworker:  sidekiq\n\nbad line\n",
+        );
+        assert_eq!(
+            summary(&procfile),
+            vec![
+                ("web", "bundle exec rails s -p $PORT", None),
+                ("worker", "sidekiq", None)
+            ]
+        );
+
+        let package = r#"{
+            "name": "app",
+            "scripts": {
+                "dev": "vite",
+                "prebuild": "rm -rf dist",
+                "build": "vite build",
+                "postinstall": "patch-package",
+                "preview": "vite preview",
+                "test:unit": "vitest"
+            }
+        }"#;
+        assert_eq!(
+            summary(&package_json(package, "pnpm").unwrap()),
+            vec![
+                ("build", "pnpm run build", Some("vite build")),
+                ("dev", "pnpm run dev", Some("vite")),
+                ("preview", "pnpm run preview", Some("vite preview")),
+                ("test:unit", "pnpm run test:unit", Some("vitest")),
+            ]
+        );
+        assert!(package_json(r#"{"name": "lib"}"#, "npm")
+            .unwrap()
+            .is_empty());
+        assert!(package_json("{", "npm").is_err());
+    }
+
+    #[test]
+    fn test_makefile() {
+        let content = "\
+CC := gcc
+VERSION = 1.0
+SHELL ::= /bin/bash
+POSIX :::= 1
+debug: CFLAGS=-g
+.PHONY: build test
+
+# Build everything
+build: deps
+\t$(CC) -o app main.c
+
+test: build ## Run the tests
+\t./app --test
+
+main.o: main.c
+%.o: %.c
+$(BIN): build
+lint fmt:
+\tcargo fmt
+";
+        assert_eq!(
+            summary(&makefile(content)),
+            vec![
+                ("build", "make build", Some("Build everything")),
+                ("test", "make test", Some("Run the tests")),
+                ("lint", "make lint", None),
+                ("fmt", "make fmt", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_justfile() {
+        let content = "\
+set dotenv-load
+alias b := build
+version := \"1.0\"
+
+# Build the app
+build target mode=\"debug\":
+    cargo build --target {{target}} --{{mode}}
+
+[private]
+helper:
+    echo hidden
+
+_also-hidden:
+    echo hidden
+
+@serve port=env_var('PORT') *flags: build
+    ./serve {{port}} {{flags}}
+
+url host=\"http://localhost:8080\":
+    curl {{host}}
+";
+        let recipes = justfile(content);
+        assert_eq!(
+            summary(&recipes),
+            vec![
+                (
+                    "build",
+                    "just build {{target}} {{mode}}",
+                    Some("Build the app")
+                ),
+                ("serve", "just serve {{port}} {{flags}}", None),
+                ("url", "just url {{host}}", None),
+            ]
+        );
+
+        let parameters: Vec<(&str, Option<&str>, bool)> = recipes
+            .iter()
+            .flat_map(|r| &r.parameters)
+            .map(|p| (p.name.as_str(), p.default.as_deref(), p.required))
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                ("target", None, true),
+                ("mode", Some("debug"), false),
+                ("port", None, false),
+                ("flags", None, false),
+                ("host", Some("http://localhost:8080"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cargo_aliases() {
+        let content = "\
+[alias]
+xtask = \"run --package xtask --\"
+ci = [\"test\", \"--workspace\"]
+
+[build]
+jobs = 4
+";
+        assert_eq!(
+            summary(&cargo_aliases(content).unwrap()),
+            vec![
+                ("ci", "cargo ci", Some("test --workspace")),
+                ("xtask", "cargo xtask", Some("run --package xtask --")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_commands() {
+        let old = vec![
+            imported(
+                SourceKind::Makefile,
+                "build",
+                "make build".to_string(),
+                None,
+            ),
+            imported(
+                SourceKind::PackageJson,
+                "dev",
+                "npm run dev".to_string(),
+                None,
+            ),
+            imported(
+                SourceKind::PackageJson,
+                "lint",
+                "npm run lint".to_string(),
+                None,
+            ),
+            imported(SourceKind::Procfile, "web", "rails s".to_string(), None),
+            imported(SourceKind::Procfile, "worker", "sidekiq".to_string(), None),
+        ];
+        let mut commands = old.clone();
+        commands[2].command = "npm run lint -- --fix".to_string();
+        commands[4].description = Some("Background jobs".to_string());
+        commands.push(Command {
+            source: None,
+            ..imported(SourceKind::Procfile, "mine", "echo mine".to_string(), None)
+        });
+
+        let discovered = vec![
+            // Unchanged
+            imported(
+                SourceKind::Makefile,
+                "build",
+                "make build".to_string(),
+                None,
+            ),
+            // Changed in the repository, edited by hand: kept
+            imported(
+                SourceKind::PackageJson,
+                "lint",
+                "pnpm run lint".to_string(),
+                None,
+            ),
+            // Changed in the repository
+            imported(
+                SourceKind::Procfile,
+                "web",
+                "rails s -b 0.0.0.0".to_string(),
+                None,
+            ),
+            imported(
+                SourceKind::Justfile,
+                "deploy",
+                "just deploy".to_string(),
+                None,
+            ),
+        ];
+        let report = sync_commands(&mut commands, discovered);
+
+        assert_eq!(
+            report,
+            SyncReport {
+                added: vec!["deploy".to_string()],
+                updated: vec!["web".to_string()],
+                removed: vec!["dev".to_string()],
+                kept: vec!["worker".to_string(), "lint".to_string()],
+            }
+        );
+        let names: Vec<&str> = commands.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["build", "lint", "web", "worker", "mine", "deploy"]
+        );
+        // Updated in place, so its id and anything else set on it stay
+        assert_eq!(commands[2].id, old[3].id);
+        assert_eq!(commands[2].command, "rails s -b 0.0.0.0");
+        assert!(!is_edited(&commands[2]));
+        assert_eq!(commands[1].command, "npm run lint -- --fix");
+        assert_eq!(commands[3].description.as_deref(), Some("Background jobs"));
+    }
+
+    #[test]
+    fn test_sync_keeps_edited_parameters() {
+        let recipe = |default: &str| {
+            let parameter = CommandParameter {
+                name: "target".to_string(),
+                kind: ParameterType::String,
+                required: false,
+                default: Some(default.to_string()),
+                choices: Vec::new(),
+            };
+            imported_with(
+                SourceKind::Justfile,
+                "deploy",
+                "just deploy {{target}}".to_string(),
+                None,
+                vec![parameter],
+            )
+        };
+        let mut commands = vec![recipe("staging")];
+        commands[0].parameters[0].kind = ParameterType::Choice;
+        commands[0].parameters[0].choices = vec!["staging".to_string(), "prod".to_string()];
+
+        let report = sync_commands(&mut commands, vec![recipe("prod")]);
+
+        assert_eq!(report.kept, vec!["deploy"]);
+        assert!(report.updated.is_empty());
+        assert_eq!(commands[0].parameters[0].kind, ParameterType::Choice);
+        assert_eq!(
+            commands[0].parameters[0].default.as_deref(),
+            Some("staging")
+        );
+
+        // Unedited, the new default is picked up
+        let mut commands = vec![recipe("staging")];
+        let report = sync_commands(&mut commands, vec![recipe("prod")]);
+        assert_eq!(report.updated, vec!["deploy"]);
+        assert_eq!(commands[0].parameters[0].default.as_deref(), Some("prod"));
+        assert!(!is_edited(&commands[0]));
+    }
+}
@@ -0,0 +1,81 @@
+//! Builders for the projects, workspaces and panes tests are written
+//! against. Tests change the fields they care about on the result.
+
+use super::{Command, ConfigLayer, Environment, Pane, Project, Workspace, WorkspaceLayout};
+use std::collections::HashMap;
+
+/// A project named "App" at `path`, with nothing configured
+pub fn project(path: &str) -> Project {
+    Project {
+        id: "p1".to_string(),
+        name: "App".to_string(),
+        path: path.to_string(),
+        description: None,
+        tags: Vec::new(),
+        environments: HashMap::new(),
+        workspaces: Vec::new(),
+        commands: Vec::new(),
+        last_opened: None,
+        created_at: "2024-01-01T00:00:00Z".to_string(),
+    }
+}
+
+/// A grid layout with one row per entry of `columns`
+pub fn layout(columns: Vec<u32>) -> WorkspaceLayout {
+    WorkspaceLayout {
+        rows: columns.len() as u32,
+        columns,
+        split: None,
+    }
+}
+
+/// A workspace named "Dev" with a grid layout, see `layout`
+pub fn workspace(columns: Vec<u32>, panes: Vec<Pane>) -> Workspace {
+    Workspace {
+        id: "w1".to_string(),
+        name: "Dev".to_string(),
+        layout: layout(columns),
+        panes,
+        default_environment: None,
+        title: None,
+        tabs: Vec::new(),
+        layer: ConfigLayer::User,
+    }
+}
+
+pub fn pane(position: (u32, u32), directory: &str, command: Option<&str>) -> Pane {
+    Pane {
+        position,
+        directory: directory.to_string(),
+        command: command.map(|c| c.to_string()),
+        environment_variables: None,
+    }
+}
+
+pub fn environment(name: &str, variables: &[(&str, &str)]) -> Environment {
+    Environment {
+        name: name.to_string(),
+        variables: variables
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        layer: ConfigLayer::User,
+        shared_variables: Vec::new(),
+    }
+}
+
+/// A command named after its id
+pub fn command(id: &str, command: &str) -> Command {
+    Command {
+        id: id.to_string(),
+        name: id.to_string(),
+        command: command.to_string(),
+        description: None,
+        icon: None,
+        parameters: Vec::new(),
+        depends_on: Vec::new(),
+        service: None,
+        source: None,
+        layer: ConfigLayer::User,
+    }
+}
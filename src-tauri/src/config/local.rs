@@ -0,0 +1,382 @@
+use super::{Command, ConfigLayer, Environment, Project, Workspace};
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the shared config file in a project's repository
+pub const LOCAL_CONFIG_FILE: &str = ".cmdr.toml";
+
+/// Contents of a project's `.cmdr.toml`: the parts of a project that are
+/// worth sharing with a team. Environments here should not hold secrets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalConfig {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environments: BTreeMap<String, Environment>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workspaces: Vec<Workspace>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<Command>,
+}
+
+impl LocalConfig {
+    fn is_empty(&self) -> bool {
+        self.environments.is_empty() && self.workspaces.is_empty() && self.commands.is_empty()
+    }
+}
+
+pub fn local_config_path(project: &Project) -> PathBuf {
+    Path::new(&project.path).join(LOCAL_CONFIG_FILE)
+}
+
+/// Read the project's `.cmdr.toml`, if it has one
+fn read_local(path: &Path) -> Result<Option<LocalConfig>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(Some(toml::from_str(&content)?))
+}
+
+/// Merge the project's `.cmdr.toml` into a project loaded from the user's
+/// file. The user's file wins: a workspace or command with the same id
+/// replaces the shared one entirely, and an environment with the same key
+/// inherits only the shared variables it does not set itself. Shared items
+/// that are not overridden come after the user's own.
+pub fn merge_local(project: &mut Project) {
+    let path = local_config_path(project);
+    let local = match read_local(&path) {
+        Ok(Some(local)) => local,
+        Ok(None) => return,
+        Err(e) => {
+            log::error!("[Config] Failed to read {:?}: {}", path, e);
+            return;
+        }
+    };
+    log::debug!("[Config] Merging {:?} into project {}", path, project.name);
+    apply_local(project, local);
+}
+
+fn apply_local(project: &mut Project, local: LocalConfig) {
+    for (key, mut environment) in local.environments {
+        match project.environments.entry(key) {
+            Entry::Vacant(entry) => {
+                environment.layer = ConfigLayer::Project;
+                entry.insert(environment);
+            }
+            Entry::Occupied(mut entry) => {
+                let own = entry.get_mut();
+                for (name, value) in environment.variables {
+                    if let Entry::Vacant(variable) = own.variables.entry(name.clone()) {
+                        variable.insert(value);
+                        own.shared_variables.push(name);
+                    }
+                }
+                own.shared_variables.sort();
+            }
+        }
+    }
+    for mut workspace in local.workspaces {
+        if !project.workspaces.iter().any(|w| w.id == workspace.id) {
+            workspace.layer = ConfigLayer::Project;
+            project.workspaces.push(workspace);
+        }
+    }
+    for mut command in local.commands {
+        if !project.commands.iter().any(|c| c.id == command.id) {
+            command.layer = ConfigLayer::Project;
+            project.commands.push(command);
+        }
+    }
+}
+
+/// Write the project-layer items back to the project's `.cmdr.toml` and
+/// return the project with only the user's own items, ready to be saved
+/// to the user's file.
+///
+/// The shared file is only written when its contents change, so that
+/// saving a project does not leave stray edits in its repository.
+pub fn write_local(project: &Project) -> Result<Project> {
+    let path = local_config_path(project);
+    let existing = match read_local(&path) {
+        Ok(existing) => existing,
+        Err(e) => {
+            log::warn!("[Config] Ignoring unreadable {:?}: {}", path, e);
+            None
+        }
+    };
+
+    let (user, local) = split_layers(project, existing.as_ref());
+    if let Some(local) = local {
+        // Compared as JSON values so that map ordering does not count
+        if serde_json::to_value(&local)? != serde_json::to_value(&existing)? {
+            log::info!("[Config] Writing shared project config to: {:?}", path);
+            fs::write(&path, toml::to_string_pretty(&local)?)?;
+        }
+    }
+
+    Ok(user)
+}
+
+/// Split a merged project into the user layer and the shared layer.
+///
+/// Shared items the user has overridden never made it into the merged
+/// project, so they are carried over from `existing` to avoid dropping
+/// them from the shared file. The variables a user environment inherited
+/// go back to its shared environment. Returns no shared layer when there is
+/// nothing to write and no file yet.
+fn split_layers(
+    project: &Project,
+    existing: Option<&LocalConfig>,
+) -> (Project, Option<LocalConfig>) {
+    let mut user = project.clone();
+    let mut local = LocalConfig::default();
+
+    user.environments.retain(|key, environment| {
+        if environment.layer.is_user() {
+            return true;
+        }
+        let mut environment = environment.clone();
+        environment.layer = ConfigLayer::User;
+        local.environments.insert(key.clone(), environment);
+        false
+    });
+    for (key, environment) in user.environments.iter_mut() {
+        let inherited = std::mem::take(&mut environment.shared_variables);
+        let base = existing.and_then(|e| e.environments.get(key));
+        if inherited.is_empty() && base.is_none() {
+            continue;
+        }
+        // Variables the user overrides keep their shared values
+        let mut shared = base.cloned().unwrap_or_else(|| Environment {
+            name: environment.name.clone(),
+            variables: HashMap::new(),
+            layer: ConfigLayer::User,
+            shared_variables: Vec::new(),
+        });
+        for name in inherited {
+            match environment.variables.remove(&name) {
+                Some(value) => shared.variables.insert(name, value),
+                // Deleted by the user
+                None => shared.variables.remove(&name),
+            };
+        }
+        local.environments.insert(key.clone(), shared);
+    }
+    local.workspaces = take_project_layer(&mut user.workspaces, |w| &mut w.layer);
+    local.commands = take_project_layer(&mut user.commands, |c| &mut c.layer);
+
+    if let Some(existing) = existing {
+        for workspace in &existing.workspaces {
+            let shadowed = user.workspaces.iter().any(|w| w.id == workspace.id);
+            if shadowed && !local.workspaces.iter().any(|w| w.id == workspace.id) {
+                local.workspaces.push(workspace.clone());
+            }
+        }
+        for command in &existing.commands {
+            let shadowed = user.commands.iter().any(|c| c.id == command.id);
+            if shadowed && !local.commands.iter().any(|c| c.id == command.id) {
+                local.commands.push(command.clone());
+            }
+        }
+    } else if local.is_empty() {
+        return (user, None);
+    }
+
+    (user, Some(local))
+}
+
+/// Remove the project-layer items from `items` and return them, marked as
+/// user items so that the marker is not written to the shared file
+fn take_project_layer<T>(items: &mut Vec<T>, layer: impl Fn(&mut T) -> &mut ConfigLayer) -> Vec<T> {
+    let mut taken = Vec::new();
+    let mut kept = Vec::new();
+    for mut item in items.drain(..) {
+        if layer(&mut item).is_user() {
+            kept.push(item);
+        } else {
+            *layer(&mut item) = ConfigLayer::User;
+            taken.push(item);
+        }
+    }
+    *items = kept;
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{self, command, environment, project};
+
+    fn workspace(id: &str) -> Workspace {
+        Workspace {
+            id: id.to_string(),
+            name: id.to_string(),
+            ..fixtures::workspace(vec![1], Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_user_layer_wins() {
+        let mut project = project("/work/app");
+        project.commands = vec![command("test", "cargo test -- --nocapture")];
+        project.environments.insert(
+            "dev".to_string(),
+            environment("dev", &[("TOKEN", "secret")]),
+        );
+
+        let local: LocalConfig = toml::from_str(
+            r#"
+            [environments.dev]
+            name = "dev"
+            variables = { API_URL = "https://dev.example.com", TOKEN = "changeme" }
+            [environments.ci]
+            name = "ci"
+            variables = { CI = "1" }
+
+            [[workspaces]]
+            id = "servers"
+            name = "Servers"
+            layout = { rows = 1, columns = [2] }
+
+            [[commands]]
+            id = "test"
+            name = "Test"
+            command = "cargo test"
+            [[commands]]
+            id = "lint"
+            name = "Lint"
+            command = "cargo clippy"
+            "#,
+        )
+        .unwrap();
+        apply_local(&mut project, local);
+
+        // The user's secret wins, the other shared variables are inherited
+        let dev = &project.environments["dev"];
+        assert_eq!(dev.variables["TOKEN"], "secret");
+        assert_eq!(dev.variables["API_URL"], "https://dev.example.com");
+        assert_eq!(dev.shared_variables, vec!["API_URL"]);
+        assert_eq!(dev.layer, ConfigLayer::User);
+        assert_eq!(project.environments["ci"].layer, ConfigLayer::Project);
+        assert_eq!(project.workspaces[0].layer, ConfigLayer::Project);
+        let commands: Vec<_> = project
+            .commands
+            .iter()
+            .map(|c| (c.command.as_str(), c.layer))
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                ("cargo test -- --nocapture", ConfigLayer::User),
+                ("cargo clippy", ConfigLayer::Project),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_layers() {
+        let mut project = project("/work/app");
+        let mut shared = workspace("servers");
+        shared.layer = ConfigLayer::Project;
+        project.workspaces = vec![workspace("mine"), shared];
+        project.commands = vec![command("test", "cargo test -q")];
+        let variables = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        project.environments.insert(
+            "dev".to_string(),
+            Environment {
+                // OLD was deleted by the user
+                shared_variables: vec!["API_URL".to_string(), "OLD".to_string()],
+                ..environment(
+                    "dev",
+                    &[
+                        ("TOKEN", "secret"),
+                        ("API_URL", "https://dev.local"),
+                        ("DEBUG", "1"),
+                    ],
+                )
+            },
+        );
+
+        let existing = LocalConfig {
+            environments: BTreeMap::from([(
+                "dev".to_string(),
+                environment(
+                    "dev",
+                    &[
+                        ("API_URL", "https://dev.example.com"),
+                        ("TOKEN", "changeme"),
+                        ("OLD", "1"),
+                    ],
+                ),
+            )]),
+            commands: vec![command("test", "cargo test"), command("old", "make")],
+            ..LocalConfig::default()
+        };
+        let (user, local) = split_layers(&project, Some(&existing));
+        let local = local.unwrap();
+
+        assert_eq!(user.workspaces.len(), 1);
+        assert_eq!(user.workspaces[0].id, "mine");
+        assert_eq!(local.workspaces.len(), 1);
+        assert_eq!(local.workspaces[0].layer, ConfigLayer::User);
+        // The overridden shared command is kept, the deleted one is dropped
+        assert_eq!(local.commands.len(), 1);
+        assert_eq!(local.commands[0].command, "cargo test");
+        // Only the user's own variables stay in the user's file, and the
+        // shared file keeps the value the user overrides
+        assert_eq!(
+            user.environments["dev"].variables,
+            variables(&[("TOKEN", "secret"), ("DEBUG", "1")])
+        );
+        assert!(user.environments["dev"].shared_variables.is_empty());
+        assert_eq!(
+            local.environments["dev"].variables,
+            variables(&[("API_URL", "https://dev.local"), ("TOKEN", "changeme")])
+        );
+        // The layer marker is never written to either file
+        assert!(!toml::to_string(&local).unwrap().contains("layer"));
+
+        // Nothing shared and no file yet: no file is created
+        let (_, local) = split_layers(&user, None);
+        assert!(local.is_none());
+    }
+
+    #[test]
+    fn test_write_local_only_when_changed() {
+        let root = std::env::temp_dir().join(format!("cmdr-local-test-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let file = root.join(LOCAL_CONFIG_FILE);
+        fs::write(
+            &file,
+            "# Shared commands\n[[commands]]\nid = \"lint\"\nname = \"lint\"\ncommand = \"cargo clippy\"\n",
+        )
+        .unwrap();
+
+        let mut loaded = project(&root.to_string_lossy());
+        merge_local(&mut loaded);
+
+        // Unchanged shared items leave the file as it was
+        let user = write_local(&loaded).unwrap();
+        let untouched = fs::read_to_string(&file).unwrap();
+
+        loaded.commands[0].command = "cargo clippy -- -D warnings".to_string();
+        write_local(&loaded).unwrap();
+        let mut reloaded = project(&root.to_string_lossy());
+        merge_local(&mut reloaded);
+        let _ = fs::remove_dir_all(&root);
+
+        assert!(user.commands.is_empty());
+        assert!(untouched.starts_with("# Shared commands"));
+        assert_eq!(reloaded.commands.len(), 1);
+        assert_eq!(reloaded.commands[0].command, "cargo clippy -- -D warnings");
+        assert_eq!(reloaded.commands[0].layer, ConfigLayer::Project);
+    }
+}
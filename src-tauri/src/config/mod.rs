@@ -1,6 +1,15 @@
+mod discover;
+#[cfg(test)]
+pub mod fixtures;
+mod local;
+mod pipeline;
 mod schema;
+mod validate;
 
+pub use discover::{discover_commands, sync_commands, SyncReport};
+pub use pipeline::{command_pipeline, PipelineStep};
 pub use schema::*;
+pub use validate::{validate_project, validate_workspace, ValidationIssue};
 
 use crate::error::{CmdrError, Result};
 use std::fs;
@@ -51,8 +60,12 @@ pub fn load_settings() -> Result<AppSettings> {
 
     let content = fs::read_to_string(&config_file)?;
     let settings: AppSettings = toml::from_str(&content)?;
-    log::info!("[Config] Settings loaded: terminal={}, theme={}, behavior={}",
-        settings.default_terminal, settings.theme, settings.terminal_behavior);
+    log::info!(
+        "[Config] Settings loaded: terminal={}, theme={}, behavior={}",
+        settings.default_terminal,
+        settings.theme,
+        settings.terminal_behavior
+    );
     Ok(settings)
 }
 
@@ -66,7 +79,7 @@ pub fn save_settings(settings: &AppSettings) -> Result<()> {
     Ok(())
 }
 
-/// Load all projects
+/// Load all projects, each merged with the `.cmdr.toml` in its directory
 pub fn load_projects() -> Result<Vec<Project>> {
     let projects_dir = get_projects_dir()?;
     log::info!("[Config] Loading projects from: {:?}", projects_dir);
@@ -79,7 +92,7 @@ pub fn load_projects() -> Result<Vec<Project>> {
         };
         let path = entry.path();
 
-        if path.extension().map_or(false, |ext| ext == "toml") {
+        if path.extension().is_some_and(|ext| ext == "toml") {
             let content = match fs::read_to_string(&path) {
                 Ok(c) => c,
                 Err(e) => {
@@ -88,7 +101,8 @@ pub fn load_projects() -> Result<Vec<Project>> {
                 }
             };
             match toml::from_str::<Project>(&content) {
-                Ok(project) => {
+                Ok(mut project) => {
+                    local::merge_local(&mut project);
                     log::debug!("[Config] Loaded project: {} ({})", project.name, project.id);
                     projects.push(project);
                 }
@@ -102,8 +116,8 @@ pub fn load_projects() -> Result<Vec<Project>> {
 
     // Sort by last opened (most recent first)
     projects.sort_by(|a, b| {
-        let a_time = a.last_opened.as_ref().map(|s| s.as_str()).unwrap_or("");
-        let b_time = b.last_opened.as_ref().map(|s| s.as_str()).unwrap_or("");
+        let a_time = a.last_opened.as_deref().unwrap_or("");
+        let b_time = b.last_opened.as_deref().unwrap_or("");
         b_time.cmp(a_time)
     });
 
@@ -111,12 +125,18 @@ pub fn load_projects() -> Result<Vec<Project>> {
     Ok(projects)
 }
 
-/// Save a project
+/// Save a project, writing project-layer items to its `.cmdr.toml` and the
+/// rest to the user's project file
 pub fn save_project(project: &Project) -> Result<()> {
     let projects_dir = get_projects_dir()?;
     let file_path = projects_dir.join(format!("{}.toml", project.id));
-    log::info!("[Config] Saving project {} to: {:?}", project.name, file_path);
-    let content = toml::to_string_pretty(project)?;
+    log::info!(
+        "[Config] Saving project {} to: {:?}",
+        project.name,
+        file_path
+    );
+    let user = local::write_local(project)?;
+    let content = toml::to_string_pretty(&user)?;
     fs::write(file_path, content)?;
     log::info!("[Config] Project saved successfully");
     Ok(())
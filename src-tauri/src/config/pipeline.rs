@@ -0,0 +1,173 @@
+use super::Command;
+use crate::error::{CmdrError, Result};
+use std::collections::HashMap;
+
+/// A command of a pipeline and the steps it waits for
+#[derive(Debug, Clone)]
+pub struct PipelineStep {
+    pub command: Command,
+    /// Indices of earlier steps that must succeed first
+    pub depends_on: Vec<usize>,
+}
+
+/// The commands to run for `command_id`: its dependencies, theirs, and so
+/// on, ordered so that every step comes after what it depends on. The
+/// command itself is the last step. Dependencies are looked up by id, then
+/// by name, in `commands` first and in `global_commands` after.
+pub fn command_pipeline(
+    commands: &[Command],
+    global_commands: &[Command],
+    command_id: &str,
+) -> Result<Vec<PipelineStep>> {
+    let find = |reference: &str| {
+        let all = || commands.iter().chain(global_commands);
+        all()
+            .find(|c| c.id == reference)
+            .or_else(|| all().find(|c| c.name == reference))
+    };
+    let target = find(command_id)
+        .ok_or_else(|| CmdrError::Pipeline(format!("Unknown command: {}", command_id)))?;
+
+    let mut builder = Builder {
+        find: &find,
+        steps: Vec::new(),
+        indices: HashMap::new(),
+        path: Vec::new(),
+    };
+    builder.visit(target)?;
+    Ok(builder.steps)
+}
+
+struct Builder<'a, F> {
+    find: &'a F,
+    steps: Vec<PipelineStep>,
+    /// Step index of each command id already added
+    indices: HashMap<String, usize>,
+    /// Commands being visited, to spot cycles
+    path: Vec<&'a Command>,
+}
+
+impl<'a, F: Fn(&str) -> Option<&'a Command>> Builder<'a, F> {
+    fn visit(&mut self, command: &'a Command) -> Result<usize> {
+        if let Some(&index) = self.indices.get(&command.id) {
+            return Ok(index);
+        }
+        if let Some(start) = self.path.iter().position(|c| c.id == command.id) {
+            let cycle: Vec<&str> = self.path[start..]
+                .iter()
+                .chain([&command])
+                .map(|c| c.name.as_str())
+                .collect();
+            return Err(CmdrError::Pipeline(format!(
+                "Commands depend on each other: {}",
+                cycle.join(" → ")
+            )));
+        }
+
+        self.path.push(command);
+        let mut depends_on = Vec::new();
+        for reference in &command.depends_on {
+            let dependency = (self.find)(reference).ok_or_else(|| {
+                CmdrError::Pipeline(format!(
+                    "{} depends on an unknown command: {}",
+                    command.name, reference
+                ))
+            })?;
+            let index = self.visit(dependency)?;
+            if !depends_on.contains(&index) {
+                depends_on.push(index);
+            }
+        }
+        self.path.pop();
+
+        self.steps.push(PipelineStep {
+            command: command.clone(),
+            depends_on,
+        });
+        let index = self.steps.len() - 1;
+        self.indices.insert(command.id.clone(), index);
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures;
+
+    fn command(id: &str, depends_on: &[&str]) -> Command {
+        Command {
+            name: format!("{} name", id),
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            ..fixtures::command(id, &format!("run {}", id))
+        }
+    }
+
+    fn order(steps: &[PipelineStep]) -> Vec<(&str, Vec<usize>)> {
+        steps
+            .iter()
+            .map(|s| (s.command.id.as_str(), s.depends_on.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_command_pipeline() {
+        let commands = vec![
+            command("serve", &["seed", "assets"]),
+            command("seed", &["migrate"]),
+            command("migrate", &["install"]),
+            command("assets", &["install name"]),
+            command("lint", &[]),
+        ];
+        let global = vec![command("install", &[])];
+
+        let steps = command_pipeline(&commands, &global, "serve").unwrap();
+        assert_eq!(
+            order(&steps),
+            vec![
+                ("install", vec![]),
+                ("migrate", vec![0]),
+                ("seed", vec![1]),
+                ("assets", vec![0]),
+                ("serve", vec![2, 3]),
+            ]
+        );
+
+        // Looked up by name too, and a command on its own is one step
+        assert_eq!(
+            order(&command_pipeline(&commands, &global, "lint name").unwrap()),
+            vec![("lint", vec![])]
+        );
+        assert!(command_pipeline(&commands, &global, "missing").is_err());
+    }
+
+    #[test]
+    fn test_command_pipeline_errors() {
+        let commands = vec![
+            command("a", &["b"]),
+            command("b", &["c"]),
+            command("c", &["a"]),
+            command("d", &["nope"]),
+            command("e", &["e"]),
+        ];
+
+        let message = |id: &str| {
+            command_pipeline(&commands, &[], id)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            message("a"),
+            "Pipeline error: Commands depend on each other: a name → b name → c name → a name"
+        );
+        assert_eq!(
+            message("d"),
+            "Pipeline error: d name depends on an unknown command: nope"
+        );
+        assert_eq!(
+            message("e"),
+            "Pipeline error: Commands depend on each other: e name → e name"
+        );
+        assert_eq!(message("f"), "Pipeline error: Unknown command: f");
+    }
+}
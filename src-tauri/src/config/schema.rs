@@ -12,6 +12,9 @@ pub struct AppSettings {
     pub default_projects_path: Option<String>,
     #[serde(default = "default_terminal_behavior", alias = "terminal_behavior")]
     pub terminal_behavior: String, // "new_window" or "use_existing"
+    /// Terminals to try, in order, when the default one is not available
+    #[serde(default, alias = "fallback_terminals")]
+    pub fallback_terminals: Vec<String>,
     #[serde(default, alias = "global_commands")]
     pub global_commands: Vec<Command>,
 }
@@ -35,6 +38,7 @@ impl Default for AppSettings {
             theme: default_theme(),
             default_projects_path: None,
             terminal_behavior: default_terminal_behavior(),
+            fallback_terminals: Vec::new(),
             global_commands: Vec::new(),
         }
     }
@@ -62,29 +66,136 @@ pub struct Project {
     pub created_at: String,
 }
 
+impl Project {
+    /// Find an environment by its key in `environments` or by its name
+    pub fn environment(&self, name: &str) -> Option<&Environment> {
+        self.environments
+            .get(name)
+            .or_else(|| self.environments.values().find(|e| e.name == name))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Environment {
     pub name: String,
     #[serde(default)]
     pub variables: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "ConfigLayer::is_user")]
+    pub layer: ConfigLayer,
+    /// Variables of a user environment that it inherits from the shared
+    /// environment with the same key, which are saved back to `.cmdr.toml`
+    #[serde(
+        default,
+        rename = "sharedVariables",
+        alias = "shared_variables",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub shared_variables: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Which file a workspace, command or environment of a project lives in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigLayer {
+    /// The user's own `projects/<uuid>.toml`
+    #[default]
+    User,
+    /// The `.cmdr.toml` checked into the project's repository
+    Project,
+}
+
+impl ConfigLayer {
+    pub fn is_user(&self) -> bool {
+        *self == ConfigLayer::User
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Workspace {
     pub id: String,
     pub name: String,
     pub layout: WorkspaceLayout,
     #[serde(default)]
     pub panes: Vec<Pane>,
+    /// Name of the project environment used when none is chosen at launch
+    #[serde(default, alias = "default_environment")]
+    pub default_environment: Option<String>,
+    /// Title of the first tab, the one holding `layout` and `panes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Further tabs opened after the first one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tabs: Vec<WorkspaceTab>,
+    #[serde(default, skip_serializing_if = "ConfigLayer::is_user")]
+    pub layer: ConfigLayer,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// An extra tab of a workspace with its own layout and panes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceTab {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Window the tab opens in; the workspace's first tab is in window 0
+    #[serde(default)]
+    pub window: u32,
+    pub layout: WorkspaceLayout,
+    #[serde(default)]
+    pub panes: Vec<Pane>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WorkspaceLayout {
+    #[serde(default)]
     pub rows: u32,
+    #[serde(default)]
     pub columns: Vec<u32>,
+    /// Split tree used instead of the rows/columns grid when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split: Option<LayoutNode>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A node of a split-tree layout: a split of its children along
+/// `direction`, or a leaf showing one of the workspace's panes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LayoutNode {
+    Split {
+        direction: SplitDirection,
+        /// Percentage of the parent split; children without one share
+        /// whatever is left
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<u32>,
+        children: Vec<LayoutNode>,
+    },
+    Pane {
+        /// Index into the workspace's `panes`, or a plain shell in the
+        /// project directory when unset
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pane: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<u32>,
+    },
+}
+
+impl LayoutNode {
+    pub fn size(&self) -> Option<u32> {
+        match self {
+            LayoutNode::Split { size, .. } | LayoutNode::Pane { size, .. } => *size,
+        }
+    }
+}
+
+/// `Horizontal` lays children out left to right, `Vertical` top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Pane {
     pub position: (u32, u32),
@@ -104,6 +215,143 @@ pub struct Command {
     pub description: Option<String>,
     #[serde(default)]
     pub icon: Option<String>,
+    /// Values the `{{name}}` and `{{input:name}}` placeholders in `command`
+    /// are filled with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<CommandParameter>,
+    /// Ids or names of project or global commands that must succeed before
+    /// this one runs
+    #[serde(
+        default,
+        rename = "dependsOn",
+        alias = "depends_on",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub depends_on: Vec<String>,
+    /// Set on long-running commands that cmdr keeps running as services of
+    /// the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<ServiceOptions>,
+    /// Set on commands imported from a file in the project's repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<CommandSource>,
+    #[serde(default, skip_serializing_if = "ConfigLayer::is_user")]
+    pub layer: ConfigLayer,
+}
+
+/// Where an imported command came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandSource {
+    pub kind: SourceKind,
+    /// Name of the process, script, target, recipe or alias
+    pub name: String,
+    /// The command as last imported; a command that no longer matches it
+    /// was edited and is left alone by syncing
+    pub imported: ImportedCommand,
+}
+
+/// The fields of a command as they were imported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedCommand {
+    pub name: String,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<CommandParameter>,
+}
+
+impl ImportedCommand {
+    pub fn of(command: &Command) -> Self {
+        Self {
+            name: command.name.clone(),
+            command: command.command.clone(),
+            description: command.description.clone(),
+            parameters: command.parameters.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceKind {
+    Procfile,
+    PackageJson,
+    Makefile,
+    Justfile,
+    CargoAlias,
+}
+
+/// How the supervisor runs a service command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceOptions {
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// Failed restarts in a row before the supervisor gives up
+    #[serde(default = "default_max_restarts", alias = "max_restarts")]
+    pub max_restarts: u32,
+    /// Environment the service always runs with, instead of the one chosen
+    /// when starting the project's services
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+}
+
+fn default_max_restarts() -> u32 {
+    5
+}
+
+impl Default for ServiceOptions {
+    fn default() -> Self {
+        Self {
+            restart: RestartPolicy::default(),
+            max_restarts: default_max_restarts(),
+            environment: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    /// Restart after a non-zero exit, waiting longer after each failure
+    #[serde(alias = "on_failure")]
+    OnFailure,
+}
+
+/// A value a command asks for before it runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandParameter {
+    pub name: String,
+    #[serde(default, rename = "type")]
+    pub kind: ParameterType,
+    /// Used when no value is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// Allowed values of a `choice` parameter
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ParameterType {
+    #[default]
+    String,
+    Number,
+    Boolean,
+    Choice,
 }
 
 /// Input type for adding a new project (without id and created_at)
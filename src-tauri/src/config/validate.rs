@@ -0,0 +1,365 @@
+use super::{LayoutNode, Pane, Project, Workspace, WorkspaceLayout};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A layout rule a workspace can break
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationRule {
+    /// `layout.rows` differs from the number of entries in `layout.columns`
+    RowCountMismatch,
+    /// The layout has no rows, or a row with no columns
+    EmptyRow,
+    /// A pane's position is outside the grid
+    PaneOutOfBounds,
+    /// Two panes share a position
+    DuplicatePosition,
+    /// A split-tree node has no children
+    EmptySplit,
+    /// A split's child sizes are zero or leave no room for its other children
+    InvalidSize,
+    /// A split-tree leaf refers to a pane that does not exist
+    UnknownPane,
+    /// A pane is missing from the split tree or shown more than once
+    PaneNotInLayout,
+}
+
+/// One problem found in a workspace, pointing at the pane when there is one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationIssue {
+    pub workspace_id: String,
+    pub workspace_name: String,
+    /// Index into the workspace's `tabs`, or unset for its first tab
+    pub tab: Option<usize>,
+    /// Index into the tab's `panes`
+    pub pane: Option<usize>,
+    pub rule: ValidationRule,
+    pub message: String,
+}
+
+/// Check the layout and pane placement of every tab of a workspace,
+/// returning every problem found
+pub fn validate_workspace(workspace: &Workspace) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    let tabs = std::iter::once((None, &workspace.layout, &workspace.panes)).chain(
+        workspace
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (Some(i), &t.layout, &t.panes)),
+    );
+    for (tab, layout, panes) in tabs {
+        let mut issue = |pane: Option<usize>, rule: ValidationRule, message: String| {
+            let message = match tab {
+                Some(index) => format!("Tab {}: {}", index, message),
+                None => message,
+            };
+            issues.push(ValidationIssue {
+                workspace_id: workspace.id.clone(),
+                workspace_name: workspace.name.clone(),
+                tab,
+                pane,
+                rule,
+                message,
+            });
+        };
+
+        match &layout.split {
+            Some(root) => check_tree(panes, root, &mut issue),
+            None => check_grid(layout, panes, &mut issue),
+        }
+    }
+
+    issues
+}
+
+fn check_grid(
+    layout: &WorkspaceLayout,
+    panes: &[Pane],
+    issue: &mut impl FnMut(Option<usize>, ValidationRule, String),
+) {
+    let columns = &layout.columns;
+    if layout.rows as usize != columns.len() {
+        issue(
+            None,
+            ValidationRule::RowCountMismatch,
+            format!(
+                "Layout has {} rows but column counts for {}",
+                layout.rows,
+                columns.len()
+            ),
+        );
+    }
+    if columns.is_empty() {
+        issue(
+            None,
+            ValidationRule::EmptyRow,
+            "Layout has no rows".to_string(),
+        );
+    }
+    for (row, &cols) in columns.iter().enumerate() {
+        if cols == 0 {
+            issue(
+                None,
+                ValidationRule::EmptyRow,
+                format!("Row {} has no columns", row),
+            );
+        }
+    }
+
+    let mut seen: HashMap<(u32, u32), usize> = HashMap::new();
+    for (index, pane) in panes.iter().enumerate() {
+        let (row, col) = pane.position;
+        match columns.get(row as usize) {
+            None => issue(
+                Some(index),
+                ValidationRule::PaneOutOfBounds,
+                format!(
+                    "Pane {} is in row {} but the layout has {} rows",
+                    index,
+                    row,
+                    columns.len()
+                ),
+            ),
+            Some(&cols) if col >= cols => issue(
+                Some(index),
+                ValidationRule::PaneOutOfBounds,
+                format!(
+                    "Pane {} is in column {} but row {} has {} columns",
+                    index, col, row, cols
+                ),
+            ),
+            Some(_) => {}
+        }
+
+        if let Some(first) = seen.insert(pane.position, index) {
+            issue(
+                Some(index),
+                ValidationRule::DuplicatePosition,
+                format!(
+                    "Pane {} has the same position ({}, {}) as pane {}",
+                    index, row, col, first
+                ),
+            );
+        }
+    }
+}
+
+/// Panes are placed by the tree's leaves, so positions are not checked;
+/// instead every pane must appear in exactly one leaf
+fn check_tree(
+    panes: &[Pane],
+    root: &LayoutNode,
+    issue: &mut impl FnMut(Option<usize>, ValidationRule, String),
+) {
+    let mut uses = vec![0; panes.len()];
+    let mut stack = vec![root];
+
+    while let Some(node) = stack.pop() {
+        match node {
+            LayoutNode::Pane {
+                pane: Some(index), ..
+            } => match uses.get_mut(*index) {
+                Some(count) => *count += 1,
+                None => issue(
+                    None,
+                    ValidationRule::UnknownPane,
+                    format!(
+                        "Layout refers to pane {} but the workspace has {} panes",
+                        index,
+                        panes.len()
+                    ),
+                ),
+            },
+            LayoutNode::Pane { pane: None, .. } => {}
+            LayoutNode::Split { children, .. } => {
+                if children.is_empty() {
+                    issue(
+                        None,
+                        ValidationRule::EmptySplit,
+                        "Layout has a split with no children".to_string(),
+                    );
+                }
+
+                let sizes: Vec<u32> = children.iter().filter_map(|c| c.size()).collect();
+                let total: u32 = sizes.iter().sum();
+                // Children without a size need some of the space left over
+                let unsized_children = sizes.len() < children.len();
+                if sizes.contains(&0) || total > 100 || (unsized_children && total == 100) {
+                    issue(
+                        None,
+                        ValidationRule::InvalidSize,
+                        format!(
+                            "Split sizes {:?} must be above 0 and leave room for every child",
+                            sizes
+                        ),
+                    );
+                }
+
+                stack.extend(children.iter().rev());
+            }
+        }
+    }
+
+    for (index, count) in uses.into_iter().enumerate() {
+        if count != 1 {
+            issue(
+                Some(index),
+                ValidationRule::PaneNotInLayout,
+                format!(
+                    "Pane {} appears {} times in the layout, expected once",
+                    index, count
+                ),
+            );
+        }
+    }
+}
+
+/// Validate the workspaces of a project that are new or changed compared
+/// with `previous`, the project as stored, so that a broken workspace that
+/// was already there does not block unrelated edits
+pub fn validate_project(project: &Project, previous: Option<&Project>) -> Vec<ValidationIssue> {
+    project
+        .workspaces
+        .iter()
+        .filter(|w| !previous.is_some_and(|p| p.workspaces.contains(w)))
+        .flat_map(validate_workspace)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{self, pane};
+    use crate::config::WorkspaceTab;
+
+    fn workspace(rows: u32, columns: Vec<u32>, positions: &[(u32, u32)]) -> Workspace {
+        let panes = positions.iter().map(|&p| pane(p, ".", None)).collect();
+        let mut workspace = fixtures::workspace(columns, panes);
+        workspace.layout.rows = rows;
+        workspace
+    }
+
+    fn rules(issues: &[ValidationIssue]) -> Vec<(Option<usize>, ValidationRule)> {
+        issues.iter().map(|i| (i.pane, i.rule)).collect()
+    }
+
+    #[test]
+    fn test_valid_workspace() {
+        let ws = workspace(2, vec![2, 1], &[(0, 0), (0, 1), (1, 0)]);
+        assert!(validate_workspace(&ws).is_empty());
+    }
+
+    #[test]
+    fn test_layout_rules() {
+        assert_eq!(
+            rules(&validate_workspace(&workspace(3, vec![2, 0], &[]))),
+            vec![
+                (None, ValidationRule::RowCountMismatch),
+                (None, ValidationRule::EmptyRow),
+            ]
+        );
+        assert_eq!(
+            rules(&validate_workspace(&workspace(0, Vec::new(), &[]))),
+            vec![(None, ValidationRule::EmptyRow)]
+        );
+    }
+
+    #[test]
+    fn test_pane_rules() {
+        let ws = workspace(2, vec![2, 1], &[(0, 1), (1, 1), (2, 0), (0, 1)]);
+        let issues = validate_workspace(&ws);
+
+        assert_eq!(
+            rules(&issues),
+            vec![
+                (Some(1), ValidationRule::PaneOutOfBounds),
+                (Some(2), ValidationRule::PaneOutOfBounds),
+                (Some(3), ValidationRule::DuplicatePosition),
+            ]
+        );
+        assert_eq!(
+            issues[1].message,
+            "Pane 2 is in row 2 but the layout has 2 rows"
+        );
+        assert_eq!(
+            issues[2].message,
+            "Pane 3 has the same position (0, 1) as pane 0"
+        );
+    }
+
+    #[test]
+    fn test_validate_project() {
+        let broken = workspace(3, vec![2, 0], &[]);
+        let mut stored = fixtures::project("/work/app");
+        stored.workspaces = vec![broken.clone()];
+        let mut project = stored.clone();
+        project.name = "Renamed".to_string();
+
+        // A broken workspace that was already stored does not block the edit
+        assert_eq!(validate_project(&project, None).len(), 2);
+        assert!(validate_project(&project, Some(&stored)).is_empty());
+
+        // Changing it does
+        project.workspaces[0].name = "Still broken".to_string();
+        assert_eq!(validate_project(&project, Some(&stored)).len(), 2);
+        stored.workspaces.clear();
+        assert_eq!(validate_project(&project, Some(&stored)).len(), 2);
+    }
+
+    #[test]
+    fn test_tab_rules() {
+        let mut ws = workspace(1, vec![1], &[(0, 0)]);
+        let logs = workspace(1, vec![2], &[(0, 2)]);
+        ws.tabs = vec![WorkspaceTab {
+            title: Some("logs".to_string()),
+            window: 1,
+            layout: logs.layout,
+            panes: logs.panes,
+        }];
+
+        let issues = validate_workspace(&ws);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].tab, Some(0));
+        assert_eq!(issues[0].pane, Some(0));
+        assert_eq!(
+            issues[0].message,
+            "Tab 0: Pane 0 is in column 2 but row 0 has 2 columns"
+        );
+    }
+
+    #[test]
+    fn test_tree_rules() {
+        let leaf = |pane: Option<usize>, size: Option<u32>| LayoutNode::Pane { pane, size };
+        let mut ws = workspace(0, Vec::new(), &[(0, 0), (0, 0), (0, 0)]);
+        ws.layout.split = Some(LayoutNode::Split {
+            direction: crate::config::SplitDirection::Horizontal,
+            size: None,
+            children: vec![
+                leaf(Some(0), Some(70)),
+                LayoutNode::Split {
+                    direction: crate::config::SplitDirection::Vertical,
+                    size: Some(40),
+                    children: vec![
+                        leaf(Some(1), None),
+                        leaf(Some(1), None),
+                        leaf(Some(5), None),
+                    ],
+                },
+            ],
+        });
+
+        // Grid rules do not apply, so the shared positions are fine
+        assert_eq!(
+            rules(&validate_workspace(&ws)),
+            vec![
+                (None, ValidationRule::InvalidSize),
+                (None, ValidationRule::UnknownPane),
+                (Some(1), ValidationRule::PaneNotInLayout),
+                (Some(2), ValidationRule::PaneNotInLayout),
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::config::ValidationIssue;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -17,11 +18,34 @@ pub enum CmdrError {
     #[error("Project not found: {0}")]
     ProjectNotFound(String),
 
+    #[error("Environment not found: {0}")]
+    EnvironmentNotFound(String),
+
+    #[error("Invalid workspace: {}", describe_issues(.0))]
+    Validation(Vec<ValidationIssue>),
+
     #[error("Config directory not found")]
     ConfigDirNotFound,
 
     #[error("Terminal error: {0}")]
     Terminal(String),
+
+    #[error("Run error: {0}")]
+    Run(String),
+
+    #[error("Pipeline error: {0}")]
+    Pipeline(String),
+
+    #[error("Invalid command parameters: {}", .0.join("; "))]
+    Parameters(Vec<String>),
+}
+
+fn describe_issues(issues: &[ValidationIssue]) -> String {
+    issues
+        .iter()
+        .map(|i| format!("{}: {}", i.workspace_name, i.message))
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 pub type Result<T> = std::result::Result<T, CmdrError>;
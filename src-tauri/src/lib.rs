@@ -1,6 +1,8 @@
+pub mod cli;
 mod commands;
 mod config;
 mod error;
+mod runner;
 mod terminal;
 
 use commands::{projects, settings};
@@ -18,20 +20,49 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .manage(runner::RunnerState::default())
+        .manage(runner::SupervisorState::default())
         .invoke_handler(tauri::generate_handler![
             // Project commands
             projects::get_projects,
             projects::add_project,
             projects::update_project,
+            projects::validate_workspace,
+            projects::discover_commands,
+            projects::sync_project_commands,
             projects::delete_project,
             projects::open_project,
             // Settings commands
             settings::get_settings,
             settings::save_settings,
             // Terminal commands
+            terminal::detect_terminals,
+            terminal::get_command_inputs,
+            terminal::import_tmux_session,
             terminal::launch_workspace,
+            terminal::list_running_workspaces,
+            terminal::list_tmux_sessions,
             terminal::open_terminal,
+            terminal::preview_launch,
             terminal::run_command,
+            terminal::stop_workspace,
+            // Runner commands
+            runner::start_run,
+            runner::list_runs,
+            runner::write_run,
+            runner::resize_run,
+            runner::kill_run,
+            runner::get_run_history,
+            runner::clear_run_history,
+            runner::start_pipeline,
+            runner::list_pipelines,
+            runner::cancel_pipeline,
+            // Supervisor commands
+            runner::start_services,
+            runner::stop_services,
+            runner::restart_service,
+            runner::list_services,
+            runner::get_service_logs,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,319 @@
+use crate::error::{CmdrError, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+/// Directory in the config directory with one history file per project
+const HISTORY_DIR: &str = "history";
+/// Runs kept per project; older ones are dropped
+const MAX_RECORDS: usize = 200;
+/// Bytes of output kept per run, from the end where the errors usually are
+pub const MAX_OUTPUT: usize = 16 * 1024;
+
+/// Held while a history file is read and written back, since runs can
+/// finish at the same time on their own threads
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+fn lock() -> MutexGuard<'static, ()> {
+    HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// How a run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+    /// Stopped from cmdr before it finished
+    Killed,
+}
+
+/// A finished run of a command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    pub run_id: String,
+    #[serde(alias = "project_id")]
+    pub project_id: String,
+    /// Id of the project or global command that was run, if any
+    #[serde(default, alias = "command_id")]
+    pub command_id: Option<String>,
+    /// The command line as it was run
+    pub command: String,
+    #[serde(default)]
+    pub environment: Option<String>,
+    pub cwd: String,
+    #[serde(alias = "started_at")]
+    pub started_at: String,
+    #[serde(alias = "finished_at")]
+    pub finished_at: String,
+    #[serde(alias = "duration_ms")]
+    pub duration_ms: u64,
+    #[serde(default, alias = "exit_code")]
+    pub exit_code: Option<i32>,
+    pub status: RunStatus,
+    /// The end of the run's output
+    #[serde(default)]
+    pub output: String,
+    /// Set when the start of the output was cut off
+    #[serde(default, alias = "output_truncated")]
+    pub output_truncated: bool,
+}
+
+/// Which runs to return from the history
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryFilter {
+    #[serde(default, alias = "command_id")]
+    pub command_id: Option<String>,
+    #[serde(default)]
+    pub status: Option<RunStatus>,
+    /// Only runs started at or after this RFC 3339 time
+    #[serde(default)]
+    pub since: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// The last `MAX_OUTPUT` bytes of a run's output
+#[derive(Debug, Default)]
+pub struct OutputTail {
+    pub text: String,
+    pub truncated: bool,
+}
+
+impl OutputTail {
+    pub fn push(&mut self, chunk: &str) {
+        self.text.push_str(chunk);
+        if self.text.len() <= MAX_OUTPUT {
+            return;
+        }
+
+        let mut cut = self.text.len() - MAX_OUTPUT;
+        while !self.text.is_char_boundary(cut) {
+            cut += 1;
+        }
+        self.text.drain(..cut);
+        self.truncated = true;
+    }
+}
+
+fn history_file(project_id: &str) -> Result<PathBuf> {
+    // Ids become file names, so nothing that could leave the directory
+    if project_id.is_empty()
+        || !project_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(CmdrError::ProjectNotFound(project_id.to_string()));
+    }
+
+    let dir = crate::config::get_config_dir()?.join(HISTORY_DIR);
+    if !dir.exists() {
+        log::info!("[History] Creating history directory: {:?}", dir);
+        std::fs::create_dir_all(&dir)?;
+    }
+    Ok(dir.join(format!("{}.json", project_id)))
+}
+
+/// A project's runs, oldest first
+pub fn load(project_id: &str) -> Result<Vec<RunRecord>> {
+    let path = history_file(project_id)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn save(project_id: &str, records: &[RunRecord]) -> Result<()> {
+    let path = history_file(project_id)?;
+    log::debug!("[History] Saving {} runs to: {:?}", records.len(), path);
+    // Written next to the file and renamed over it, so that the file is
+    // never read half written
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, serde_json::to_string_pretty(records)?)?;
+    std::fs::rename(&temp, &path)?;
+    Ok(())
+}
+
+/// Add a finished run to its project's history
+pub fn record(run: RunRecord) -> Result<()> {
+    let project_id = run.project_id.clone();
+    let _guard = lock();
+    let mut records = load(&project_id)?;
+    records.push(run);
+    if records.len() > MAX_RECORDS {
+        records.drain(..records.len() - MAX_RECORDS);
+    }
+    save(&project_id, &records)
+}
+
+/// Project ids that have a history file
+fn projects_with_history() -> Result<Vec<String>> {
+    let dir = crate::config::get_config_dir()?.join(HISTORY_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut ids = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(stem) = path.file_stem() {
+                ids.push(stem.to_string_lossy().to_string());
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Runs of one project, or of all projects, that match `filter`, newest
+/// first
+pub fn query(project_id: Option<&str>, filter: &HistoryFilter) -> Result<Vec<RunRecord>> {
+    let projects = match project_id {
+        Some(id) => vec![id.to_string()],
+        None => projects_with_history()?,
+    };
+
+    let mut records = Vec::new();
+    for id in &projects {
+        records.extend(load(id)?);
+    }
+    Ok(apply_filter(records, filter))
+}
+
+/// Remove runs of one project, or of all projects, optionally only those
+/// of one command
+pub fn clear(project_id: Option<&str>, command_id: Option<&str>) -> Result<()> {
+    let projects = match project_id {
+        Some(id) => vec![id.to_string()],
+        None => projects_with_history()?,
+    };
+
+    let _guard = lock();
+    for id in &projects {
+        match command_id {
+            Some(command_id) => {
+                let mut records = load(id)?;
+                records.retain(|r| r.command_id.as_deref() != Some(command_id));
+                save(id, &records)?;
+            }
+            None => {
+                let path = history_file(id)?;
+                if path.exists() {
+                    log::info!("[History] Deleting history file: {:?}", path);
+                    std::fs::remove_file(path)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply_filter(mut records: Vec<RunRecord>, filter: &HistoryFilter) -> Vec<RunRecord> {
+    records.retain(|r| {
+        filter
+            .command_id
+            .as_ref()
+            .is_none_or(|id| r.command_id.as_ref() == Some(id))
+            && filter.status.is_none_or(|status| r.status == status)
+            && filter
+                .since
+                .as_ref()
+                .is_none_or(|since| compare_times(&r.started_at, since) != Ordering::Less)
+    });
+    records.sort_by(|a, b| compare_times(&b.started_at, &a.started_at));
+    if let Some(limit) = filter.limit {
+        records.truncate(limit);
+    }
+    records
+}
+
+/// Compare RFC 3339 times, falling back to comparing the text
+fn compare_times(a: &str, b: &str) -> Ordering {
+    match (
+        chrono::DateTime::parse_from_rfc3339(a),
+        chrono::DateTime::parse_from_rfc3339(b),
+    ) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: &str, command_id: &str, started_at: &str, status: RunStatus) -> RunRecord {
+        RunRecord {
+            run_id: id.to_string(),
+            project_id: "p1".to_string(),
+            command_id: Some(command_id.to_string()),
+            command: "cargo build".to_string(),
+            environment: None,
+            cwd: "/work/app".to_string(),
+            started_at: started_at.to_string(),
+            finished_at: started_at.to_string(),
+            duration_ms: 1200,
+            exit_code: Some(if status == RunStatus::Succeeded { 0 } else { 1 }),
+            status,
+            output: String::new(),
+            output_truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_apply_filter() {
+        let records = vec![
+            run("1", "build", "2024-05-01T10:00:00Z", RunStatus::Succeeded),
+            run("2", "test", "2024-05-01T11:00:00Z", RunStatus::Failed),
+            run("3", "build", "2024-05-01T12:15:00+01:00", RunStatus::Failed),
+            run("4", "build", "2024-05-01T12:30:00Z", RunStatus::Killed),
+        ];
+
+        let ids = |filter: HistoryFilter| -> Vec<String> {
+            apply_filter(records.clone(), &filter)
+                .into_iter()
+                .map(|r| r.run_id)
+                .collect()
+        };
+
+        assert_eq!(ids(HistoryFilter::default()), vec!["4", "3", "2", "1"]);
+        let builds = HistoryFilter {
+            command_id: Some("build".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(builds.clone()), vec!["4", "3", "1"]);
+        assert_eq!(
+            ids(HistoryFilter {
+                status: Some(RunStatus::Failed),
+                limit: Some(1),
+                ..builds
+            }),
+            vec!["3"]
+        );
+        // 12:15+01:00 is 11:15 UTC
+        assert_eq!(
+            ids(HistoryFilter {
+                since: Some("2024-05-01T10:30:00Z".to_string()),
+                ..Default::default()
+            }),
+            vec!["4", "3", "2"]
+        );
+    }
+
+    #[test]
+    fn test_output_tail() {
+        let mut tail = OutputTail::default();
+        tail.push("start\n");
+        assert!(!tail.truncated);
+
+        tail.push(&"é".repeat(MAX_OUTPUT));
+        tail.push("end");
+        assert!(tail.truncated);
+        assert!(tail.text.len() <= MAX_OUTPUT);
+        assert!(tail.text.ends_with("éend"));
+    }
+}
@@ -0,0 +1,309 @@
+mod history;
+mod pipeline;
+mod pty;
+mod supervisor;
+
+pub use history::{HistoryFilter, RunRecord, RunStatus};
+pub use pipeline::{cancel_pipeline, list_pipelines, start_pipeline};
+pub use pty::PtyProcess;
+pub use supervisor::{
+    get_service_logs, list_services, restart_service, start_services, stop_services,
+    SupervisorState,
+};
+
+use crate::error::{CmdrError, Result};
+use crate::terminal::PanePlan;
+use history::OutputTail;
+use pipeline::Pipeline;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, State};
+
+/// Event carrying a chunk of a run's terminal output
+pub const OUTPUT_EVENT: &str = "run-output";
+/// Event sent once when a run's process has exited
+pub const EXIT_EVENT: &str = "run-exit";
+/// Event carrying a run that was started, also by a pipeline
+pub const START_EVENT: &str = "run-start";
+
+/// A command running inside cmdr rather than in an external terminal
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunInfo {
+    pub run_id: String,
+    pub project_id: String,
+    pub command_id: Option<String>,
+    pub command: String,
+    pub environment: Option<String>,
+    /// Pipeline that started the run, if any
+    pub pipeline_id: Option<String>,
+    pub cwd: String,
+    pub pid: Option<u32>,
+    pub started_at: String,
+}
+
+impl RunInfo {
+    fn new(
+        project_id: String,
+        command_id: Option<String>,
+        environment: Option<String>,
+        pipeline_id: Option<String>,
+        pane: &PanePlan,
+    ) -> Self {
+        Self {
+            run_id: uuid::Uuid::new_v4().to_string(),
+            project_id,
+            command_id,
+            // As run, with its placeholders filled in
+            command: pane.commands.join("; "),
+            environment,
+            pipeline_id,
+            cwd: pane.cwd.clone(),
+            pid: None,
+            started_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunOutput {
+    pub run_id: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunExit {
+    pub run_id: String,
+    /// `None` when the exit status could not be read
+    pub exit_code: Option<i32>,
+    pub status: RunStatus,
+}
+
+struct Run {
+    info: RunInfo,
+    process: PtyProcess,
+    /// Set by `kill_run`, so the history can tell kills from failures
+    killed: Arc<AtomicBool>,
+}
+
+type Runs = Arc<Mutex<HashMap<String, Run>>>;
+
+/// Runs whose processes are still alive and pipelines that are not done,
+/// managed by Tauri
+#[derive(Default)]
+pub struct RunnerState {
+    runs: Runs,
+    pipelines: Arc<Mutex<HashMap<String, Pipeline>>>,
+}
+
+/// Do something with a live run, or fail when it has already exited
+fn with_run<T>(runs: &Runs, run_id: &str, f: impl FnOnce(&mut Run) -> Result<T>) -> Result<T> {
+    let mut runs = runs.lock().unwrap_or_else(|e| e.into_inner());
+    let run = runs
+        .get_mut(run_id)
+        .ok_or_else(|| CmdrError::Run(format!("No running command with id {}", run_id)))?;
+    f(run)
+}
+
+/// Start a project command in a pseudo-terminal under the project's path,
+/// with the chosen environment and its placeholders filled in from
+/// `values`. Output arrives as `run-output` events and
+/// the exit code as a `run-exit` event; the finished run is added to the
+/// project's history.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn start_run(
+    app: AppHandle,
+    state: State<'_, RunnerState>,
+    project_id: String,
+    command: String,
+    command_id: Option<String>,
+    environment: Option<String>,
+    values: Option<HashMap<String, String>>,
+    cols: Option<u16>,
+    rows: Option<u16>,
+) -> Result<RunInfo> {
+    log::info!("[Runner] Running '{}' for project {}", command, project_id);
+
+    let pane = crate::terminal::command_plan(
+        &project_id,
+        &command,
+        command_id.as_deref(),
+        environment.as_deref(),
+        &values.unwrap_or_default(),
+    )?;
+    let info = RunInfo::new(project_id, command_id, environment, None, &pane);
+    spawn_run(&app, &state.runs, info, &pane, (cols, rows), |_| {})
+}
+
+/// Start a run in a pseudo-terminal and register it until it exits.
+/// `on_finish` is called with how it ended, after its history record and
+/// `run-exit` event.
+fn spawn_run(
+    app: &AppHandle,
+    runs: &Runs,
+    info: RunInfo,
+    pane: &PanePlan,
+    (cols, rows): (Option<u16>, Option<u16>),
+    on_finish: impl FnOnce(RunStatus) + Send + 'static,
+) -> Result<RunInfo> {
+    // Held until the run is registered, so a command that exits at once
+    // cannot be removed before it was added
+    let mut live = runs.lock().unwrap_or_else(|e| e.into_inner());
+
+    let tail = Arc::new(Mutex::new(OutputTail::default()));
+    let killed = Arc::new(AtomicBool::new(false));
+    let started = Instant::now();
+
+    let output_app = app.clone();
+    let output_id = info.run_id.clone();
+    let output_tail = Arc::clone(&tail);
+    let exit_app = app.clone();
+    let exit_runs = Arc::clone(runs);
+    let exit_info = info.clone();
+    let exit_killed = Arc::clone(&killed);
+    let process = PtyProcess::spawn(
+        pane,
+        cols.unwrap_or(pty::DEFAULT_COLS),
+        rows.unwrap_or(pty::DEFAULT_ROWS),
+        move |data| {
+            output_tail
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(&data);
+            let payload = RunOutput {
+                run_id: output_id.clone(),
+                data,
+            };
+            if let Err(e) = output_app.emit(OUTPUT_EVENT, payload) {
+                log::warn!("[Runner] Failed to send output of {}: {:?}", output_id, e);
+            }
+        },
+        move |exit_code| {
+            let run_id = exit_info.run_id.clone();
+            exit_runs
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&run_id);
+
+            let status = match exit_code {
+                _ if exit_killed.load(Ordering::SeqCst) => RunStatus::Killed,
+                Some(0) => RunStatus::Succeeded,
+                _ => RunStatus::Failed,
+            };
+            let tail = std::mem::take(&mut *tail.lock().unwrap_or_else(|e| e.into_inner()));
+            let record = RunRecord {
+                run_id: run_id.clone(),
+                project_id: exit_info.project_id,
+                command_id: exit_info.command_id,
+                command: exit_info.command,
+                environment: exit_info.environment,
+                cwd: exit_info.cwd,
+                started_at: exit_info.started_at,
+                finished_at: chrono::Utc::now().to_rfc3339(),
+                duration_ms: started.elapsed().as_millis() as u64,
+                exit_code,
+                status,
+                output: tail.text,
+                output_truncated: tail.truncated,
+            };
+            if let Err(e) = history::record(record) {
+                log::error!("[Runner] Failed to record run {} in history: {}", run_id, e);
+            }
+
+            let payload = RunExit {
+                run_id: run_id.clone(),
+                exit_code,
+                status,
+            };
+            if let Err(e) = exit_app.emit(EXIT_EVENT, payload) {
+                log::warn!("[Runner] Failed to send exit of {}: {:?}", run_id, e);
+            }
+            on_finish(status);
+        },
+    )?;
+
+    let info = RunInfo {
+        pid: process.pid(),
+        ..info
+    };
+    live.insert(
+        info.run_id.clone(),
+        Run {
+            info: info.clone(),
+            process,
+            killed,
+        },
+    );
+    if let Err(e) = app.emit(START_EVENT, info.clone()) {
+        log::warn!("[Runner] Failed to send start of {}: {:?}", info.run_id, e);
+    }
+
+    Ok(info)
+}
+
+/// Kill a live run, remembering that it was killed rather than failed
+fn kill(runs: &Runs, run_id: &str) -> Result<()> {
+    with_run(runs, run_id, |run| {
+        run.killed.store(true, Ordering::SeqCst);
+        run.process.kill()
+    })
+}
+
+/// Commands still running, for a window that was reloaded
+#[tauri::command]
+pub fn list_runs(state: State<'_, RunnerState>) -> Vec<RunInfo> {
+    let runs = state.runs.lock().unwrap_or_else(|e| e.into_inner());
+    runs.values().map(|run| run.info.clone()).collect()
+}
+
+/// Send keyboard input to a run
+#[tauri::command]
+pub fn write_run(state: State<'_, RunnerState>, run_id: String, data: String) -> Result<()> {
+    with_run(&state.runs, &run_id, |run| run.process.write(&data))
+}
+
+/// Tell a run's terminal that the output view changed size
+#[tauri::command]
+pub fn resize_run(
+    state: State<'_, RunnerState>,
+    run_id: String,
+    cols: u16,
+    rows: u16,
+) -> Result<()> {
+    with_run(&state.runs, &run_id, |run| run.process.resize(cols, rows))
+}
+
+/// Kill a run; its `run-exit` event follows once the process is gone
+#[tauri::command]
+pub fn kill_run(state: State<'_, RunnerState>, run_id: String) -> Result<()> {
+    log::info!("[Runner] Killing run {}", run_id);
+    kill(&state.runs, &run_id)
+}
+
+/// Finished runs, newest first, of one project or of all of them
+#[tauri::command]
+pub fn get_run_history(
+    project_id: Option<String>,
+    filter: Option<HistoryFilter>,
+) -> Result<Vec<RunRecord>> {
+    log::debug!("[Runner] Getting run history for {:?}", project_id);
+    history::query(project_id.as_deref(), &filter.unwrap_or_default())
+}
+
+/// Forget finished runs of one project or of all of them, optionally only
+/// those of one command
+#[tauri::command]
+pub fn clear_run_history(project_id: Option<String>, command_id: Option<String>) -> Result<()> {
+    log::info!(
+        "[Runner] Clearing run history for {:?} {:?}",
+        project_id,
+        command_id
+    );
+    history::clear(project_id.as_deref(), command_id.as_deref())
+}
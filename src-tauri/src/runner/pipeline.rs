@@ -0,0 +1,476 @@
+use super::{kill, spawn_run, RunInfo, RunStatus, RunnerState, Runs};
+use crate::config::PipelineStep;
+use crate::error::{CmdrError, Result};
+use crate::terminal::PanePlan;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Emitter, State};
+
+/// Event carrying the whole state of a pipeline whenever a step changes
+pub const PIPELINE_EVENT: &str = "pipeline-update";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Killed,
+    /// Not run because a step failed or the pipeline was cancelled
+    Skipped,
+}
+
+impl From<RunStatus> for StepStatus {
+    fn from(status: RunStatus) -> Self {
+        match status {
+            RunStatus::Succeeded => StepStatus::Succeeded,
+            RunStatus::Failed => StepStatus::Failed,
+            RunStatus::Killed => StepStatus::Killed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PipelineStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepInfo {
+    pub command_id: String,
+    pub name: String,
+    /// The command line as it runs, with its placeholders filled in
+    pub command: String,
+    /// Indices of the steps that must succeed first
+    pub depends_on: Vec<usize>,
+    pub status: StepStatus,
+    /// Set once the step has started
+    pub run_id: Option<String>,
+}
+
+/// A command running together with everything it depends on
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineInfo {
+    pub pipeline_id: String,
+    pub project_id: String,
+    /// The command the pipeline was started for, its last step
+    pub command_id: String,
+    pub name: String,
+    pub environment: Option<String>,
+    /// Whether steps that do not depend on each other run at the same time
+    pub parallel: bool,
+    pub status: PipelineStatus,
+    pub steps: Vec<StepInfo>,
+    pub started_at: String,
+}
+
+impl PipelineInfo {
+    fn new(
+        project_id: String,
+        environment: Option<String>,
+        parallel: bool,
+        steps: &[(PipelineStep, PanePlan)],
+    ) -> Self {
+        let (last, _) = steps.last().expect("a pipeline ends with its command");
+        Self {
+            pipeline_id: uuid::Uuid::new_v4().to_string(),
+            project_id,
+            command_id: last.command.id.clone(),
+            name: last.command.name.clone(),
+            environment,
+            parallel,
+            status: PipelineStatus::Running,
+            steps: steps
+                .iter()
+                .map(|(step, pane)| StepInfo {
+                    command_id: step.command.id.clone(),
+                    name: step.command.name.clone(),
+                    command: pane.commands.join("; "),
+                    depends_on: step.depends_on.clone(),
+                    status: StepStatus::Pending,
+                    run_id: None,
+                })
+                .collect(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Steps that can start now: pending, with every dependency succeeded,
+    /// and only one at a time unless the pipeline is parallel
+    fn ready(&self) -> Vec<usize> {
+        let ready = self.steps.iter().enumerate().filter(|(_, step)| {
+            step.status == StepStatus::Pending
+                && step
+                    .depends_on
+                    .iter()
+                    .all(|&d| self.steps[d].status == StepStatus::Succeeded)
+        });
+
+        if self.parallel {
+            ready.map(|(index, _)| index).collect()
+        } else if self.steps.iter().any(|s| s.status == StepStatus::Running) {
+            Vec::new()
+        } else {
+            ready.map(|(index, _)| index).take(1).collect()
+        }
+    }
+
+    /// Start the ready steps with `start`, which returns the step's run id.
+    /// A step that fails to start fails the pipeline like any other.
+    fn start_ready(&mut self, mut start: impl FnMut(usize, &StepInfo) -> Result<String>) {
+        for index in self.ready() {
+            // Skipped when a step before it failed to start
+            if self.steps[index].status != StepStatus::Pending {
+                continue;
+            }
+            match start(index, &self.steps[index]) {
+                Ok(run_id) => {
+                    self.steps[index].status = StepStatus::Running;
+                    self.steps[index].run_id = Some(run_id);
+                }
+                Err(e) => {
+                    log::error!(
+                        "[Pipeline] Failed to start {}: {}",
+                        self.steps[index].name,
+                        e
+                    );
+                    self.finish(index, StepStatus::Failed);
+                }
+            }
+        }
+    }
+
+    /// Record how a step ended. Anything else going wrong stops the
+    /// pipeline; steps already running are left to finish.
+    fn finish(&mut self, index: usize, status: StepStatus) {
+        self.steps[index].status = status;
+        if status != StepStatus::Succeeded {
+            self.skip_pending();
+        }
+    }
+
+    fn skip_pending(&mut self) {
+        for step in &mut self.steps {
+            if step.status == StepStatus::Pending {
+                step.status = StepStatus::Skipped;
+            }
+        }
+    }
+
+    /// Work out the pipeline's status once no step is running or can start
+    fn settle(&mut self, cancelled: bool) {
+        if self.steps.iter().any(|s| s.status == StepStatus::Running) || !self.ready().is_empty() {
+            return;
+        }
+        self.status = if cancelled {
+            PipelineStatus::Cancelled
+        } else if self.steps.iter().all(|s| s.status == StepStatus::Succeeded) {
+            PipelineStatus::Succeeded
+        } else {
+            PipelineStatus::Failed
+        };
+    }
+}
+
+pub(super) struct Pipeline {
+    info: PipelineInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+type Pipelines = Arc<Mutex<HashMap<String, Pipeline>>>;
+
+/// Run a saved command after the commands it depends on, each in its own
+/// pseudo-terminal. Steps start once their dependencies succeeded, side by
+/// side unless `parallel` is false; the first failure stops the pipeline.
+/// Progress arrives as `pipeline-update` events.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn start_pipeline(
+    app: AppHandle,
+    state: State<'_, RunnerState>,
+    project_id: String,
+    command_id: String,
+    environment: Option<String>,
+    values: Option<HashMap<String, String>>,
+    parallel: Option<bool>,
+    cols: Option<u16>,
+    rows: Option<u16>,
+) -> Result<PipelineInfo> {
+    log::info!(
+        "[Pipeline] Starting {} for project {}",
+        command_id,
+        project_id
+    );
+
+    let plans = crate::terminal::pipeline_plan(
+        &project_id,
+        &command_id,
+        environment.as_deref(),
+        &values.unwrap_or_default(),
+    )?;
+    let info = PipelineInfo::new(project_id, environment, parallel.unwrap_or(true), &plans);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    state
+        .pipelines
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(
+            info.pipeline_id.clone(),
+            Pipeline {
+                info: info.clone(),
+                cancelled: Arc::clone(&cancelled),
+            },
+        );
+
+    let runs = Arc::clone(&state.runs);
+    let pipelines = Arc::clone(&state.pipelines);
+    let panes = plans.into_iter().map(|(_, pane)| pane).collect();
+    let driven = info.clone();
+    thread::spawn(move || drive(app, runs, pipelines, driven, panes, (cols, rows), cancelled));
+
+    Ok(info)
+}
+
+/// Start steps as they become ready until the pipeline is done
+fn drive(
+    app: AppHandle,
+    runs: Runs,
+    pipelines: Pipelines,
+    mut info: PipelineInfo,
+    panes: Vec<PanePlan>,
+    size: (Option<u16>, Option<u16>),
+    cancelled: Arc<AtomicBool>,
+) {
+    let (finished_tx, finished_rx) = mpsc::channel();
+
+    loop {
+        {
+            // Held while starting steps, so `cancel_pipeline` either stops
+            // them from starting or sees them to kill
+            let mut live = pipelines.lock().unwrap_or_else(|e| e.into_inner());
+            let cancelled = cancelled.load(Ordering::SeqCst);
+            if cancelled {
+                info.skip_pending();
+            }
+
+            let (project_id, environment, pipeline_id) = (
+                info.project_id.clone(),
+                info.environment.clone(),
+                info.pipeline_id.clone(),
+            );
+            info.start_ready(|index, step| {
+                let pane = &panes[index];
+                let run = RunInfo::new(
+                    project_id.clone(),
+                    Some(step.command_id.clone()),
+                    environment.clone(),
+                    Some(pipeline_id.clone()),
+                    pane,
+                );
+                let finished_tx = finished_tx.clone();
+                let on_finish = move |status: RunStatus| {
+                    let _ = finished_tx.send((index, status));
+                };
+                spawn_run(&app, &runs, run, pane, size, on_finish).map(|run| run.run_id)
+            });
+
+            info.settle(cancelled);
+            if let Some(pipeline) = live.get_mut(&info.pipeline_id) {
+                pipeline.info = info.clone();
+            }
+            if info.status != PipelineStatus::Running {
+                live.remove(&info.pipeline_id);
+            }
+        }
+
+        if let Err(e) = app.emit(PIPELINE_EVENT, info.clone()) {
+            log::warn!(
+                "[Pipeline] Failed to send update of {}: {:?}",
+                info.pipeline_id,
+                e
+            );
+        }
+        if info.status != PipelineStatus::Running {
+            log::info!("[Pipeline] {} finished: {:?}", info.name, info.status);
+            return;
+        }
+
+        match finished_rx.recv() {
+            Ok((index, status)) => info.finish(index, status.into()),
+            Err(_) => return,
+        }
+    }
+}
+
+/// Pipelines that are not done yet, for a window that was reloaded
+#[tauri::command]
+pub fn list_pipelines(state: State<'_, RunnerState>) -> Vec<PipelineInfo> {
+    let pipelines = state.pipelines.lock().unwrap_or_else(|e| e.into_inner());
+    pipelines.values().map(|p| p.info.clone()).collect()
+}
+
+/// Stop a pipeline: nothing more is started and running steps are killed
+#[tauri::command]
+pub fn cancel_pipeline(state: State<'_, RunnerState>, pipeline_id: String) -> Result<()> {
+    log::info!("[Pipeline] Cancelling {}", pipeline_id);
+    let pipelines = state.pipelines.lock().unwrap_or_else(|e| e.into_inner());
+    let pipeline = pipelines
+        .get(&pipeline_id)
+        .ok_or_else(|| CmdrError::Run(format!("No running pipeline with id {}", pipeline_id)))?;
+
+    pipeline.cancelled.store(true, Ordering::SeqCst);
+    let running = pipeline
+        .info
+        .steps
+        .iter()
+        .filter(|s| s.status == StepStatus::Running)
+        .filter_map(|s| s.run_id.as_deref());
+    for run_id in running {
+        // A step may have exited on its own in the meantime
+        if let Err(e) = kill(&state.runs, run_id) {
+            log::warn!("[Pipeline] Failed to kill run {}: {}", run_id, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline(parallel: bool, depends_on: &[&[usize]]) -> PipelineInfo {
+        PipelineInfo {
+            pipeline_id: "p".to_string(),
+            project_id: "project".to_string(),
+            command_id: "serve".to_string(),
+            name: "Serve".to_string(),
+            environment: None,
+            parallel,
+            status: PipelineStatus::Running,
+            steps: depends_on
+                .iter()
+                .enumerate()
+                .map(|(index, depends_on)| StepInfo {
+                    command_id: index.to_string(),
+                    name: index.to_string(),
+                    command: format!("step {}", index),
+                    depends_on: depends_on.to_vec(),
+                    status: StepStatus::Pending,
+                    run_id: None,
+                })
+                .collect(),
+            started_at: "2024-05-01T10:00:00Z".to_string(),
+        }
+    }
+
+    fn start(info: &mut PipelineInfo) -> Vec<usize> {
+        let ready = info.ready();
+        for &index in &ready {
+            info.steps[index].status = StepStatus::Running;
+        }
+        ready
+    }
+
+    #[test]
+    fn test_pipeline_order() {
+        // install, then migrate and assets side by side, then serve
+        let mut info = pipeline(true, &[&[], &[0], &[0], &[1, 2]]);
+        assert_eq!(start(&mut info), vec![0]);
+        assert!(info.ready().is_empty());
+
+        info.finish(0, StepStatus::Succeeded);
+        assert_eq!(start(&mut info), vec![1, 2]);
+        info.finish(1, StepStatus::Succeeded);
+        assert!(info.ready().is_empty());
+        info.finish(2, StepStatus::Succeeded);
+        assert_eq!(start(&mut info), vec![3]);
+
+        info.settle(false);
+        assert_eq!(info.status, PipelineStatus::Running);
+        info.finish(3, StepStatus::Succeeded);
+        info.settle(false);
+        assert_eq!(info.status, PipelineStatus::Succeeded);
+
+        // One step at a time when not parallel
+        let mut info = pipeline(false, &[&[], &[0], &[0], &[1, 2]]);
+        assert_eq!(start(&mut info), vec![0]);
+        info.finish(0, StepStatus::Succeeded);
+        assert_eq!(start(&mut info), vec![1]);
+        assert!(info.ready().is_empty());
+        info.finish(1, StepStatus::Succeeded);
+        assert_eq!(start(&mut info), vec![2]);
+    }
+
+    #[test]
+    fn test_pipeline_failure() {
+        let mut info = pipeline(true, &[&[], &[0], &[0], &[1, 2]]);
+        start(&mut info);
+        info.finish(0, StepStatus::Succeeded);
+        start(&mut info);
+
+        // The other running step finishes, nothing else starts
+        info.finish(1, StepStatus::Failed);
+        let statuses: Vec<StepStatus> = info.steps.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                StepStatus::Succeeded,
+                StepStatus::Failed,
+                StepStatus::Running,
+                StepStatus::Skipped
+            ]
+        );
+        info.settle(false);
+        assert_eq!(info.status, PipelineStatus::Running);
+
+        info.finish(2, StepStatus::Succeeded);
+        assert!(info.ready().is_empty());
+        info.settle(false);
+        assert_eq!(info.status, PipelineStatus::Failed);
+
+        let mut info = pipeline(true, &[&[], &[0]]);
+        start(&mut info);
+        info.finish(0, StepStatus::Killed);
+        info.settle(true);
+        assert_eq!(info.status, PipelineStatus::Cancelled);
+        assert_eq!(info.steps[1].status, StepStatus::Skipped);
+    }
+
+    #[test]
+    fn test_pipeline_start_failure() {
+        // Two independent steps are ready, the first fails to start
+        let mut info = pipeline(true, &[&[], &[], &[0, 1]]);
+        let mut started = Vec::new();
+        info.start_ready(|index, _| {
+            started.push(index);
+            match index {
+                0 => Err(CmdrError::Pipeline("no pty".to_string())),
+                _ => Ok(format!("run-{}", index)),
+            }
+        });
+
+        assert_eq!(started, vec![0]);
+        let statuses: Vec<StepStatus> = info.steps.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            vec![StepStatus::Failed, StepStatus::Skipped, StepStatus::Skipped]
+        );
+        info.settle(false);
+        assert_eq!(info.status, PipelineStatus::Failed);
+
+        let mut info = pipeline(true, &[&[]]);
+        info.start_ready(|index, _| Ok(format!("run-{}", index)));
+        assert_eq!(info.steps[0].status, StepStatus::Running);
+        assert_eq!(info.steps[0].run_id.as_deref(), Some("run-0"));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::error::{CmdrError, Result};
+use crate::terminal::PanePlan;
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use std::io::{Read, Write};
+use std::thread;
+
+/// Size of a pseudo-terminal before the frontend reports its own
+pub const DEFAULT_COLS: u16 = 120;
+pub const DEFAULT_ROWS: u16 = 30;
+
+const READ_BUFFER: usize = 8192;
+
+/// A command running in a pseudo-terminal owned by cmdr
+pub struct PtyProcess {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    killer: Box<dyn ChildKiller + Send + Sync>,
+    pid: Option<u32>,
+}
+
+impl PtyProcess {
+    /// Start a pane's commands in a new pseudo-terminal. `on_output` gets
+    /// everything the command prints, stdout and stderr alike; `on_exit`
+    /// gets its exit code once the output is drained.
+    pub fn spawn(
+        pane: &PanePlan,
+        cols: u16,
+        rows: u16,
+        mut on_output: impl FnMut(String) + Send + 'static,
+        on_exit: impl FnOnce(Option<i32>) + Send + 'static,
+    ) -> Result<Self> {
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(pty_error)?;
+
+        let mut cmd = shell_command(&pane.commands.join("; "));
+        cmd.cwd(&pane.cwd);
+        for (name, value) in &pane.env {
+            cmd.env(name, value);
+        }
+        if std::env::var_os("TERM").is_none() {
+            cmd.env("TERM", "xterm-256color");
+        }
+
+        log::info!("[Runner] Starting {:?} in {}", pane.commands, pane.cwd);
+        let mut child = pair.slave.spawn_command(cmd).map_err(pty_error)?;
+        // Only the child may hold the terminal's other end, or reading
+        // would never see it close
+        drop(pair.slave);
+
+        let pid = child.process_id();
+        let killer = child.clone_killer();
+        let mut reader = pair.master.try_clone_reader().map_err(pty_error)?;
+        let writer = pair.master.take_writer().map_err(pty_error)?;
+
+        thread::spawn(move || {
+            let mut decoder = Utf8Decoder::default();
+            let mut buffer = [0u8; READ_BUFFER];
+            loop {
+                match reader.read(&mut buffer) {
+                    // Linux reports the closed terminal as an error
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => {
+                        let text = decoder.decode(&buffer[..read]);
+                        if !text.is_empty() {
+                            on_output(text);
+                        }
+                    }
+                }
+            }
+
+            let code = match child.wait() {
+                Ok(status) => Some(status.exit_code() as i32),
+                Err(e) => {
+                    log::error!("[Runner] Failed to wait for process: {}", e);
+                    None
+                }
+            };
+            log::info!("[Runner] Process {:?} exited with {:?}", pid, code);
+            on_exit(code);
+        });
+
+        Ok(Self {
+            master: pair.master,
+            writer,
+            killer,
+            pid,
+        })
+    }
+
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Type into the terminal, as if on the keyboard
+    pub fn write(&mut self, data: &str) -> Result<()> {
+        self.writer.write_all(data.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        log::debug!("[Runner] Resizing {:?} to {}x{}", self.pid, cols, rows);
+        self.master
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(pty_error)
+    }
+
+    pub fn kill(&mut self) -> Result<()> {
+        log::info!("[Runner] Killing process {:?}", self.pid);
+        self.killer.kill()?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> CommandBuilder {
+    let mut cmd = CommandBuilder::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+#[cfg(not(unix))]
+fn shell_command(command: &str) -> CommandBuilder {
+    let mut cmd = CommandBuilder::new("cmd.exe");
+    cmd.args(["/C", command]);
+    cmd
+}
+
+fn pty_error(e: impl std::fmt::Display) -> CmdrError {
+    log::error!("[Runner] Pseudo-terminal error: {}", e);
+    CmdrError::Run(format!("Pseudo-terminal error: {}", e))
+}
+
+/// Turns chunks of terminal output into text without splitting characters
+/// that straddle two reads
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // An incomplete character at the end waits for the next chunk
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            // Invalid bytes are replaced rather than held back forever
+            Err(_) => self.pending.len(),
+        };
+
+        let text = String::from_utf8_lossy(&self.pending[..complete]).to_string();
+        self.pending.drain(..complete);
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_utf8_decoder() {
+        let mut decoder = Utf8Decoder::default();
+        let bytes = "é✓".as_bytes();
+
+        assert_eq!(decoder.decode(&bytes[..1]), "");
+        assert_eq!(decoder.decode(&bytes[1..3]), "é");
+        assert_eq!(decoder.decode(&bytes[3..]), "✓");
+        assert_eq!(decoder.decode(&[b'a', 0xff, b'b']), "a\u{fffd}b");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pty_process() {
+        let dir = std::env::temp_dir().canonicalize().unwrap();
+        let pane = PanePlan {
+            position: None,
+            cwd: dir.to_string_lossy().to_string(),
+            env: BTreeMap::from([("GREETING".to_string(), "hi".to_string())]),
+            commands: vec![
+                "echo \"$GREETING $(pwd)\"; read line; echo \"got $line\"; exit 3".to_string(),
+            ],
+        };
+
+        let (output_tx, output_rx) = mpsc::channel();
+        let (exit_tx, exit_rx) = mpsc::channel();
+        let mut process = PtyProcess::spawn(
+            &pane,
+            80,
+            24,
+            move |text| {
+                let _ = output_tx.send(text);
+            },
+            move |code| {
+                let _ = exit_tx.send(code);
+            },
+        )
+        .unwrap();
+        assert!(process.pid().is_some());
+        process.resize(100, 40).unwrap();
+        process.write("abc\r").unwrap();
+
+        let code = exit_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        let output: String = output_rx.try_iter().collect();
+        assert_eq!(code, Some(3));
+        assert!(
+            output.contains(&format!("hi {}", dir.display())),
+            "{}",
+            output
+        );
+        assert!(output.contains("got abc"), "{}", output);
+
+        // A killed command still reports its exit
+        let pane = PanePlan {
+            commands: vec!["sleep 30".to_string()],
+            ..pane
+        };
+        let (exit_tx, exit_rx) = mpsc::channel();
+        let mut process = PtyProcess::spawn(
+            &pane,
+            80,
+            24,
+            |_| {},
+            move |code| {
+                let _ = exit_tx.send(code);
+            },
+        )
+        .unwrap();
+        process.kill().unwrap();
+        let code = exit_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_ne!(code, Some(0));
+    }
+}
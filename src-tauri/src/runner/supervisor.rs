@@ -0,0 +1,592 @@
+use super::pty::{self, PtyProcess};
+use crate::config::{Command, RestartPolicy, ServiceOptions};
+use crate::error::{CmdrError, Result};
+use crate::terminal::PanePlan;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+/// Event carrying one prefixed line of a service's output
+pub const SERVICE_LOG_EVENT: &str = "service-log";
+/// Event carrying a service's state whenever it changes
+pub const SERVICE_STATE_EVENT: &str = "service-state";
+
+/// Log lines kept per project for a window that was reloaded
+const MAX_LOG_LINES: usize = 1000;
+/// Wait before the first restart, doubled after each failure in a row
+const BACKOFF_START: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// A service that stayed up this long starts its backoff over
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+
+/// Colors given to services in turn, as names for the frontend and ANSI
+/// codes for the log text
+const COLORS: [(&str, u8); 6] = [
+    ("cyan", 36),
+    ("yellow", 33),
+    ("green", 32),
+    ("magenta", 35),
+    ("blue", 34),
+    ("red", 31),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ServiceState {
+    Starting,
+    Running,
+    /// Being stopped or restarted on request
+    Stopping,
+    /// Waiting to restart after a failure
+    Backoff,
+    /// Exited and not restarted: it succeeded, or its policy is `never`
+    Exited,
+    /// Kept failing until the supervisor gave up, or could not start
+    Failed,
+    /// Stopped on request
+    Stopped,
+}
+
+/// A service command the supervisor runs, as reported to the frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceInfo {
+    pub project_id: String,
+    pub command_id: String,
+    pub name: String,
+    /// The command line as it runs, with its placeholders filled in
+    pub command: String,
+    pub color: String,
+    pub state: ServiceState,
+    pub pid: Option<u32>,
+    /// Restarts since the service was started, on failure or on request
+    pub restarts: u32,
+    pub exit_code: Option<i32>,
+    /// When the current process started
+    pub started_at: Option<String>,
+    /// How long until the next restart, while in backoff
+    pub retry_in_ms: Option<u64>,
+}
+
+/// One line of a service's output
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceLog {
+    pub project_id: String,
+    pub command_id: String,
+    pub name: String,
+    pub color: String,
+    pub line: String,
+    /// The line behind the service's name in its color, as a terminal
+    /// would show it
+    pub text: String,
+}
+
+enum Control {
+    Exited(Option<i32>),
+    Stop,
+    Restart,
+}
+
+struct Service {
+    info: ServiceInfo,
+    control: Sender<Control>,
+}
+
+/// Project id and command id of a service
+type ServiceKey = (String, String);
+
+/// Services of every project and their recent output, managed by Tauri
+#[derive(Clone, Default)]
+pub struct SupervisorState {
+    services: Arc<Mutex<HashMap<ServiceKey, Service>>>,
+    logs: Arc<Mutex<HashMap<String, VecDeque<ServiceLog>>>>,
+}
+
+/// What a supervising thread needs to know about its service
+#[derive(Clone)]
+struct ServiceSpec {
+    key: ServiceKey,
+    name: String,
+    pane: PanePlan,
+    options: ServiceOptions,
+    /// The name padded to the longest one of the project, so logs line up
+    prefix: String,
+    color: (&'static str, u8),
+}
+
+impl SupervisorState {
+    fn is_alive(&self, key: &ServiceKey) -> bool {
+        let services = self.services.lock().unwrap_or_else(|e| e.into_inner());
+        services
+            .get(key)
+            .is_some_and(|s| s.info.state != ServiceState::Stopped)
+    }
+
+    /// Change a service's state and tell the frontend
+    fn update(&self, app: &AppHandle, key: &ServiceKey, f: impl FnOnce(&mut ServiceInfo)) {
+        let mut services = self.services.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(service) = services.get_mut(key) else {
+            return;
+        };
+        f(&mut service.info);
+        if let Err(e) = app.emit(SERVICE_STATE_EVENT, service.info.clone()) {
+            log::warn!(
+                "[Supervisor] Failed to send state of {}: {:?}",
+                service.info.name,
+                e
+            );
+        }
+    }
+
+    fn log(&self, app: &AppHandle, spec: &ServiceSpec, line: String) {
+        let (color, code) = spec.color;
+        let log = ServiceLog {
+            project_id: spec.key.0.clone(),
+            command_id: spec.key.1.clone(),
+            name: spec.name.clone(),
+            color: color.to_string(),
+            text: format!("\x1b[{}m{} |\x1b[0m {}", code, spec.prefix, line),
+            line,
+        };
+
+        let mut logs = self.logs.lock().unwrap_or_else(|e| e.into_inner());
+        let lines = logs.entry(log.project_id.clone()).or_default();
+        lines.push_back(log.clone());
+        if lines.len() > MAX_LOG_LINES {
+            lines.pop_front();
+        }
+        drop(logs);
+
+        if let Err(e) = app.emit(SERVICE_LOG_EVENT, log) {
+            log::warn!("[Supervisor] Failed to send log of {}: {:?}", spec.name, e);
+        }
+    }
+
+    /// Register a service and start supervising it on its own thread
+    fn launch(&self, app: &AppHandle, spec: ServiceSpec) -> ServiceInfo {
+        let (control, control_rx) = mpsc::channel();
+        let info = ServiceInfo {
+            project_id: spec.key.0.clone(),
+            command_id: spec.key.1.clone(),
+            name: spec.name.clone(),
+            command: spec.pane.commands.join("; "),
+            color: spec.color.0.to_string(),
+            state: ServiceState::Starting,
+            pid: None,
+            restarts: 0,
+            exit_code: None,
+            started_at: None,
+            retry_in_ms: None,
+        };
+        self.services
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                spec.key.clone(),
+                Service {
+                    info: info.clone(),
+                    control: control.clone(),
+                },
+            );
+
+        let supervisor = self.clone();
+        let app = app.clone();
+        thread::spawn(move || supervisor.supervise(&app, &spec, control, control_rx));
+        info
+    }
+
+    /// Run a service until it is stopped, restarting it as its policy says
+    fn supervise(
+        &self,
+        app: &AppHandle,
+        spec: &ServiceSpec,
+        control: Sender<Control>,
+        control_rx: Receiver<Control>,
+    ) {
+        let key = &spec.key;
+        // Failures in a row, for the backoff
+        let mut failures = 0;
+
+        loop {
+            self.update(app, key, |info| {
+                info.state = ServiceState::Starting;
+                info.retry_in_ms = None;
+            });
+
+            let lines = Arc::new(Mutex::new(LineBuffer::default()));
+            let output_supervisor = self.clone();
+            let output_app = app.clone();
+            let output_lines = Arc::clone(&lines);
+            let output_spec = spec.clone();
+            let exited = control.clone();
+            let started = Instant::now();
+            let spawned = PtyProcess::spawn(
+                &spec.pane,
+                pty::DEFAULT_COLS,
+                pty::DEFAULT_ROWS,
+                move |text| {
+                    let complete = output_lines
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push(&text);
+                    for line in complete {
+                        output_supervisor.log(&output_app, &output_spec, line);
+                    }
+                },
+                move |code| {
+                    let _ = exited.send(Control::Exited(code));
+                },
+            );
+
+            let mut process = match spawned {
+                Ok(process) => process,
+                Err(e) => {
+                    log::error!("[Supervisor] Failed to start {}: {}", spec.name, e);
+                    self.update(app, key, |info| info.state = ServiceState::Failed);
+                    if self.idle(app, spec, &control_rx) {
+                        continue;
+                    }
+                    return;
+                }
+            };
+            log::info!("[Supervisor] Started {} as {:?}", spec.name, process.pid());
+            self.update(app, key, |info| {
+                info.state = ServiceState::Running;
+                info.pid = process.pid();
+                info.exit_code = None;
+                info.started_at = Some(chrono::Utc::now().to_rfc3339());
+            });
+
+            let mut request = None;
+            let code = loop {
+                match control_rx.recv() {
+                    Ok(Control::Exited(code)) => break code,
+                    Ok(other) => {
+                        self.update(app, key, |info| info.state = ServiceState::Stopping);
+                        if let Err(e) = process.kill() {
+                            log::warn!("[Supervisor] Failed to kill {}: {}", spec.name, e);
+                        }
+                        request = Some(other);
+                    }
+                    Err(_) => return,
+                }
+            };
+            if let Some(rest) = lines.lock().unwrap_or_else(|e| e.into_inner()).flush() {
+                self.log(app, spec, rest);
+            }
+            log::info!("[Supervisor] {} exited with {:?}", spec.name, code);
+            self.update(app, key, |info| {
+                info.pid = None;
+                info.exit_code = code;
+            });
+
+            match request {
+                Some(Control::Stop) => {
+                    self.update(app, key, |info| info.state = ServiceState::Stopped);
+                    return;
+                }
+                Some(_) => {
+                    failures = 0;
+                    self.update(app, key, |info| info.restarts += 1);
+                    continue;
+                }
+                None => {}
+            }
+
+            if started.elapsed() >= STABLE_AFTER {
+                failures = 0;
+            }
+            let Some(delay) = restart_delay(&spec.options, code, failures) else {
+                let state = match (spec.options.restart, code) {
+                    (RestartPolicy::OnFailure, code) if code != Some(0) => ServiceState::Failed,
+                    _ => ServiceState::Exited,
+                };
+                self.update(app, key, |info| info.state = state);
+                if self.idle(app, spec, &control_rx) {
+                    continue;
+                }
+                return;
+            };
+
+            log::info!("[Supervisor] Restarting {} in {:?}", spec.name, delay);
+            self.update(app, key, |info| {
+                info.state = ServiceState::Backoff;
+                info.retry_in_ms = Some(delay.as_millis() as u64);
+            });
+            match control_rx.recv_timeout(delay) {
+                Ok(Control::Stop) => {
+                    self.update(app, key, |info| info.state = ServiceState::Stopped);
+                    return;
+                }
+                // Restarted on request: the backoff starts over
+                Ok(_) => failures = 0,
+                Err(RecvTimeoutError::Timeout) => failures += 1,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            self.update(app, key, |info| info.restarts += 1);
+        }
+    }
+
+    /// Wait while a service is not running. Returns whether it should start
+    /// again; otherwise it is marked stopped.
+    fn idle(&self, app: &AppHandle, spec: &ServiceSpec, control_rx: &Receiver<Control>) -> bool {
+        loop {
+            match control_rx.recv() {
+                Ok(Control::Restart) => {
+                    self.update(app, &spec.key, |info| info.restarts += 1);
+                    return true;
+                }
+                Ok(Control::Exited(_)) => continue,
+                Ok(Control::Stop) | Err(_) => {
+                    self.update(app, &spec.key, |info| info.state = ServiceState::Stopped);
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// Start services from their plans, skipping ones already running
+    fn start(
+        &self,
+        app: &AppHandle,
+        project_id: &str,
+        plans: Vec<(Command, PanePlan)>,
+    ) -> Vec<ServiceInfo> {
+        let width = prefix_width(&plans);
+        let mut started = Vec::new();
+        for (index, (command, pane)) in plans.into_iter().enumerate() {
+            let spec = ServiceSpec::new(project_id, command, pane, index, width);
+            if self.is_alive(&spec.key) {
+                continue;
+            }
+            log::info!("[Supervisor] Starting service {}", spec.name);
+            started.push(self.launch(app, spec));
+        }
+        started
+    }
+
+    fn send(&self, key: &ServiceKey, control: Control) -> bool {
+        let services = self.services.lock().unwrap_or_else(|e| e.into_inner());
+        services
+            .get(key)
+            .filter(|s| s.info.state != ServiceState::Stopped)
+            .is_some_and(|s| s.control.send(control).is_ok())
+    }
+}
+
+impl ServiceSpec {
+    /// The `index`th service of a project whose longest service name is
+    /// `width` characters
+    fn new(project_id: &str, command: Command, pane: PanePlan, index: usize, width: usize) -> Self {
+        Self {
+            key: (project_id.to_string(), command.id.clone()),
+            prefix: format!("{:<width$}", command.name, width = width),
+            color: COLORS[index % COLORS.len()],
+            options: command.service.unwrap_or_default(),
+            name: command.name,
+            pane,
+        }
+    }
+}
+
+/// How long to wait before restarting a service that exited with `code`
+/// after `failures` failed restarts in a row, or `None` to leave it down
+fn restart_delay(options: &ServiceOptions, code: Option<i32>, failures: u32) -> Option<Duration> {
+    match options.restart {
+        RestartPolicy::Never => None,
+        RestartPolicy::OnFailure if code == Some(0) || failures >= options.max_restarts => None,
+        RestartPolicy::OnFailure => Some(
+            BACKOFF_START
+                .saturating_mul(2u32.saturating_pow(failures))
+                .min(BACKOFF_MAX),
+        ),
+    }
+}
+
+/// Splits terminal output into lines, holding back an unfinished one
+#[derive(Debug, Default)]
+struct LineBuffer {
+    partial: String,
+}
+
+impl LineBuffer {
+    fn push(&mut self, text: &str) -> Vec<String> {
+        self.partial.push_str(text);
+        let Some(end) = self.partial.rfind('\n') else {
+            return Vec::new();
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        complete
+            .lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect()
+    }
+
+    /// Whatever is left once the output has ended
+    fn flush(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.partial);
+        let rest = rest.trim_end_matches('\r');
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+}
+
+/// Longest service name of a project, which log prefixes are padded to
+fn prefix_width(plans: &[(Command, PanePlan)]) -> usize {
+    plans
+        .iter()
+        .map(|(c, _)| c.name.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Start every service of a project that is not running yet, under the
+/// project's path and without a terminal. Output arrives as `service-log`
+/// events, prefixed with the service's name; state changes as
+/// `service-state` events.
+#[tauri::command]
+pub fn start_services(
+    app: AppHandle,
+    state: State<'_, SupervisorState>,
+    project_id: String,
+    environment: Option<String>,
+) -> Result<Vec<ServiceInfo>> {
+    log::info!("[Supervisor] Starting services of project {}", project_id);
+    let plans = crate::terminal::service_plans(&project_id, None, environment.as_deref())?;
+    Ok(state.start(&app, &project_id, plans))
+}
+
+/// Stop every service of a project
+#[tauri::command]
+pub fn stop_services(state: State<'_, SupervisorState>, project_id: String) -> Result<()> {
+    log::info!("[Supervisor] Stopping services of project {}", project_id);
+    let keys: Vec<ServiceKey> = {
+        let services = state.services.lock().unwrap_or_else(|e| e.into_inner());
+        services
+            .keys()
+            .filter(|(p, _)| *p == project_id)
+            .cloned()
+            .collect()
+    };
+    for key in keys {
+        state.send(&key, Control::Stop);
+    }
+    Ok(())
+}
+
+/// Restart one service, or start it when it is not running
+#[tauri::command]
+pub fn restart_service(
+    app: AppHandle,
+    state: State<'_, SupervisorState>,
+    project_id: String,
+    command_id: String,
+    environment: Option<String>,
+) -> Result<()> {
+    log::info!(
+        "[Supervisor] Restarting service {} of project {}",
+        command_id,
+        project_id
+    );
+    let key = (project_id.clone(), command_id.clone());
+    if state.send(&key, Control::Restart) {
+        return Ok(());
+    }
+
+    // Colored and padded like the project's other services
+    let plans = crate::terminal::service_plans(&project_id, None, environment.as_deref())?;
+    let width = prefix_width(&plans);
+    let (index, (command, pane)) = plans
+        .into_iter()
+        .enumerate()
+        .find(|(_, (c, _))| c.id == command_id)
+        .ok_or_else(|| CmdrError::Run(format!("No service with id {}", command_id)))?;
+    state.launch(
+        &app,
+        ServiceSpec::new(&project_id, command, pane, index, width),
+    );
+    Ok(())
+}
+
+/// Services that were started, of one project or of all of them
+#[tauri::command]
+pub fn list_services(
+    state: State<'_, SupervisorState>,
+    project_id: Option<String>,
+) -> Vec<ServiceInfo> {
+    let services = state.services.lock().unwrap_or_else(|e| e.into_inner());
+    services
+        .values()
+        .filter(|s| {
+            project_id
+                .as_ref()
+                .is_none_or(|id| s.info.project_id == *id)
+        })
+        .map(|s| s.info.clone())
+        .collect()
+}
+
+/// Recent output of a project's services, oldest first
+#[tauri::command]
+pub fn get_service_logs(state: State<'_, SupervisorState>, project_id: String) -> Vec<ServiceLog> {
+    let logs = state.logs.lock().unwrap_or_else(|e| e.into_inner());
+    logs.get(&project_id)
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_delay() {
+        let never = ServiceOptions::default();
+        assert_eq!(restart_delay(&never, Some(1), 0), None);
+
+        let on_failure = ServiceOptions {
+            restart: RestartPolicy::OnFailure,
+            max_restarts: 8,
+            environment: None,
+        };
+        assert_eq!(restart_delay(&on_failure, Some(0), 0), None);
+        let delays: Vec<Option<u64>> = (0..9)
+            .map(|failures| restart_delay(&on_failure, Some(1), failures).map(|d| d.as_secs()))
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(1),
+                Some(2),
+                Some(4),
+                Some(8),
+                Some(16),
+                Some(30),
+                Some(30),
+                Some(30),
+                None
+            ]
+        );
+        // No exit status is a failure too
+        assert_eq!(restart_delay(&on_failure, None, 0), Some(BACKOFF_START));
+    }
+
+    #[test]
+    fn test_line_buffer() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer.push("Listening").is_empty());
+        assert_eq!(
+            buffer.push(" on 3000\r\nGET /\r\nGET"),
+            vec!["Listening on 3000", "GET /"]
+        );
+        assert_eq!(buffer.push(" /about\r\n\r\n"), vec!["GET /about", ""]);
+        assert_eq!(buffer.push("bye"), Vec::<String>::new());
+        assert_eq!(buffer.flush().as_deref(), Some("bye"));
+        assert_eq!(buffer.flush(), None);
+    }
+}
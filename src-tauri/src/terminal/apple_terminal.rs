@@ -0,0 +1,129 @@
+use super::backend::TerminalBackend;
+use super::osascript;
+use super::plan::{LaunchPlan, PanePlan};
+use crate::error::Result;
+
+/// The built-in macOS Terminal.app, driven through AppleScript
+pub struct AppleTerminal;
+
+impl TerminalBackend for AppleTerminal {
+    fn id(&self) -> &'static str {
+        "terminal"
+    }
+
+    fn name(&self) -> &'static str {
+        "Terminal.app"
+    }
+
+    fn is_available(&self) -> bool {
+        cfg!(target_os = "macos")
+    }
+
+    /// Terminal.app has no scriptable split panes, so each pane of the
+    /// layout opens as its own window
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        log::info!(
+            "[Terminal.app] Launching workspace {} as {} windows",
+            plan.workspace_name,
+            plan.panes().len()
+        );
+
+        let script = generate_applescript(plan);
+        osascript::run(&script)?;
+
+        log::info!("[Terminal.app] Workspace launched successfully");
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        osascript::run(
+            r#"
+            tell application "Terminal"
+                activate
+                do script ""
+            end tell
+            "#,
+        )
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        let line = osascript::escape_string(&pane_line(pane));
+
+        let script = if use_existing {
+            format!(
+                r#"
+                tell application "Terminal"
+                    activate
+                    if (count of windows) > 0 then
+                        do script "{}" in front window
+                    else
+                        do script "{}"
+                    end if
+                end tell
+                "#,
+                line, line
+            )
+        } else {
+            format!(
+                r#"
+                tell application "Terminal"
+                    activate
+                    do script "{}"
+                end tell
+                "#,
+                line
+            )
+        };
+
+        osascript::run(&script)
+    }
+}
+
+fn generate_applescript(plan: &LaunchPlan) -> String {
+    let mut script = String::from(
+        r#"
+tell application "Terminal"
+    activate
+"#,
+    );
+    for pane in plan.panes() {
+        script.push_str(&format!(
+            "    do script \"{}\"\n",
+            osascript::escape_string(&pane_line(pane))
+        ));
+    }
+    script.push_str("end tell\n");
+
+    log::debug!("[Terminal.app] Generated AppleScript:\n{}", script);
+
+    script
+}
+
+/// Shell line for one window: exports, then `cd`, then the commands
+fn pane_line(pane: &PanePlan) -> String {
+    pane.shell_lines().join(" && ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_pane_line() {
+        let mut pane = PanePlan {
+            position: None,
+            cwd: "/work/app".to_string(),
+            env: BTreeMap::from([("PORT".to_string(), "3000".to_string())]),
+            commands: vec!["npm start".to_string()],
+        };
+        assert_eq!(
+            pane_line(&pane),
+            "export PORT='3000' && cd '/work/app' && npm start"
+        );
+
+        pane.env.clear();
+        pane.commands.clear();
+        assert_eq!(pane_line(&pane), "cd '/work/app'");
+    }
+}
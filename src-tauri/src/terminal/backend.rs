@@ -0,0 +1,64 @@
+use super::plan::{LaunchPlan, PanePlan};
+use crate::error::{CmdrError, Result};
+
+/// A terminal application or multiplexer that cmdr can drive.
+///
+/// Backends are looked up by the `default_terminal` setting, see
+/// [`super::backend_for`].
+pub trait TerminalBackend {
+    /// Identifier stored in `AppSettings.default_terminal`
+    fn id(&self) -> &'static str;
+
+    /// Display name used in logs and errors
+    fn name(&self) -> &'static str;
+
+    /// Whether the terminal is installed and usable on this machine
+    fn is_available(&self) -> bool;
+
+    /// Whether the terminal has a server running that cmdr can talk to.
+    /// Only multiplexers and terminals with a control socket have one.
+    fn is_running(&self) -> bool {
+        false
+    }
+
+    /// Names of the sessions running in this terminal, for backends where
+    /// a workspace lives on as a named session (multiplexers)
+    fn running_sessions(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Bring a running session to the foreground
+    fn attach_session(&self, session: &str) -> Result<()> {
+        Err(CmdrError::Terminal(format!(
+            "{} cannot attach to session {}",
+            self.name(),
+            session
+        )))
+    }
+
+    /// Process ids of the shells in a running session's panes
+    fn session_pids(&self, _session: &str) -> Vec<u32> {
+        Vec::new()
+    }
+
+    /// End a running session and everything running in it
+    fn kill_session(&self, session: &str) -> Result<()> {
+        Err(CmdrError::Terminal(format!(
+            "{} cannot end session {}",
+            self.name(),
+            session
+        )))
+    }
+
+    /// Open the windows, tabs and panes of a launch plan. Each pane's
+    /// variables are exported before it changes directory and runs its
+    /// commands.
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()>;
+
+    /// Open a new, empty terminal window
+    fn open_terminal(&self) -> Result<()>;
+
+    /// Run a single pane's commands, reusing the frontmost window when
+    /// `use_existing` is set and the terminal supports it
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()>;
+}
@@ -0,0 +1,323 @@
+use crate::config::{
+    ConfigLayer, LayoutNode, Pane, Project, SplitDirection, Workspace, WorkspaceLayout,
+    WorkspaceTab,
+};
+use std::path::Path;
+
+/// Shells a pane can sit idle in; panes running only a shell get no command
+const SHELLS: &[&str] = &[
+    "sh", "bash", "zsh", "fish", "dash", "ksh", "tcsh", "csh", "nu", "elvish",
+];
+
+/// One pane of a running multiplexer session, with its geometry in cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedPane {
+    pub window: u32,
+    pub window_name: String,
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+    pub directory: String,
+    /// What runs in the pane, or `None` for an idle shell
+    pub command: Option<String>,
+}
+
+/// Whether a pane's foreground program is just its shell
+pub fn is_shell(command: &str) -> bool {
+    let name = command.rsplit('/').next().unwrap_or(command);
+    SHELLS.contains(&name.trim_start_matches('-'))
+}
+
+/// Build a workspace reproducing the captured panes: the first window
+/// becomes the workspace's own layout and every further window a tab
+pub fn build_workspace(project: &Project, name: &str, panes: &[CapturedPane]) -> Workspace {
+    let mut windows: Vec<u32> = panes.iter().map(|p| p.window).collect();
+    windows.sort_unstable();
+    windows.dedup();
+
+    let mut tabs: Vec<WorkspaceTab> = windows
+        .iter()
+        .map(|window| {
+            let mut window_panes: Vec<&CapturedPane> =
+                panes.iter().filter(|p| p.window == *window).collect();
+            window_panes.sort_by_key(|p| (p.top, p.left));
+            capture_tab(project, &window_panes)
+        })
+        .collect();
+
+    let first = if tabs.is_empty() {
+        WorkspaceTab {
+            title: None,
+            window: 0,
+            layout: WorkspaceLayout {
+                rows: 1,
+                columns: vec![1],
+                split: None,
+            },
+            panes: Vec::new(),
+        }
+    } else {
+        tabs.remove(0)
+    };
+
+    Workspace {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        layout: first.layout,
+        panes: first.panes,
+        default_environment: None,
+        title: first.title,
+        tabs,
+        layer: ConfigLayer::User,
+    }
+}
+
+/// One window's panes as a tab with a split-tree layout
+fn capture_tab(project: &Project, panes: &[&CapturedPane]) -> WorkspaceTab {
+    let indexed: Vec<(usize, &CapturedPane)> = panes.iter().copied().enumerate().collect();
+
+    WorkspaceTab {
+        title: panes
+            .first()
+            .map(|p| p.window_name.clone())
+            .filter(|n| !n.is_empty()),
+        window: 0,
+        layout: WorkspaceLayout {
+            rows: 0,
+            columns: Vec::new(),
+            split: Some(layout_tree(&indexed)),
+        },
+        panes: panes
+            .iter()
+            .enumerate()
+            .map(|(index, pane)| Pane {
+                position: (0, index as u32),
+                directory: relative_directory(&project.path, &pane.directory),
+                command: pane.command.clone(),
+                environment_variables: None,
+            })
+            .collect(),
+    }
+}
+
+/// A pane's directory relative to the project, or absolute when it lies
+/// outside of it
+fn relative_directory(project_path: &str, directory: &str) -> String {
+    match Path::new(directory).strip_prefix(project_path) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.to_string_lossy().to_string(),
+        Err(_) => directory.to_string(),
+    }
+}
+
+/// Rebuild the split tree of a window from its pane rectangles. Panes are
+/// grouped into columns wherever a vertical line crosses no pane, and
+/// otherwise into rows; each group is split the same way in turn.
+fn layout_tree(panes: &[(usize, &CapturedPane)]) -> LayoutNode {
+    if panes.len() <= 1 {
+        return LayoutNode::Pane {
+            pane: panes.first().map(|(index, _)| *index),
+            size: None,
+        };
+    }
+
+    for direction in [SplitDirection::Horizontal, SplitDirection::Vertical] {
+        let groups = group_along(panes, direction);
+        if groups.len() < 2 {
+            continue;
+        }
+
+        let extent = |p: &CapturedPane| match direction {
+            SplitDirection::Horizontal => (p.left, p.left + p.width),
+            SplitDirection::Vertical => (p.top, p.top + p.height),
+        };
+        // Each group owns the border cell after it, so spans add up to the
+        // window's size
+        let spans: Vec<u32> = groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| {
+                let start = group.iter().map(|(_, p)| extent(p).0).min().unwrap_or(0);
+                let end = group.iter().map(|(_, p)| extent(p).1).max().unwrap_or(0);
+                let border = u32::from(index + 1 < groups.len());
+                end.saturating_sub(start) + border
+            })
+            .collect();
+
+        let children = groups
+            .iter()
+            .zip(percentages(&spans))
+            .map(|(group, size)| with_size(layout_tree(group), size))
+            .collect();
+        return LayoutNode::Split {
+            direction,
+            size: None,
+            children,
+        };
+    }
+
+    // Panes that no straight line separates cannot come from splits; keep
+    // them side by side rather than lose any
+    LayoutNode::Split {
+        direction: SplitDirection::Horizontal,
+        size: None,
+        children: panes
+            .iter()
+            .map(|(index, _)| LayoutNode::Pane {
+                pane: Some(*index),
+                size: None,
+            })
+            .collect(),
+    }
+}
+
+/// Split panes into groups separated by lines across `direction` that no
+/// pane straddles
+fn group_along<'a>(
+    panes: &[(usize, &'a CapturedPane)],
+    direction: SplitDirection,
+) -> Vec<Vec<(usize, &'a CapturedPane)>> {
+    let extent = |p: &CapturedPane| match direction {
+        SplitDirection::Horizontal => (p.left, p.left + p.width),
+        SplitDirection::Vertical => (p.top, p.top + p.height),
+    };
+
+    let mut sorted = panes.to_vec();
+    sorted.sort_by_key(|(_, p)| extent(p).0);
+
+    let mut groups: Vec<Vec<(usize, &CapturedPane)>> = Vec::new();
+    let mut group_end = 0;
+    for (index, pane) in sorted {
+        let (start, end) = extent(pane);
+        match groups.last_mut() {
+            Some(group) if start < group_end => group.push((index, pane)),
+            _ => groups.push(vec![(index, pane)]),
+        }
+        group_end = group_end.max(end);
+    }
+
+    // Keep the panes of each group in their original order
+    for group in &mut groups {
+        group.sort_by_key(|(index, _)| *index);
+    }
+    groups
+}
+
+/// Whole percentages of `spans` that add up to 100, none below 1
+fn percentages(spans: &[u32]) -> Vec<u32> {
+    let total: u32 = spans.iter().sum::<u32>().max(1);
+    let mut sizes: Vec<u32> = spans
+        .iter()
+        .map(|span| (span * 100 / total).max(1))
+        .collect();
+    let head: u32 = sizes[..sizes.len() - 1].iter().sum();
+    if let Some(last) = sizes.last_mut() {
+        *last = 100u32.saturating_sub(head).max(1);
+    }
+    sizes
+}
+
+fn with_size(node: LayoutNode, percent: u32) -> LayoutNode {
+    match node {
+        LayoutNode::Pane { pane, .. } => LayoutNode::Pane {
+            pane,
+            size: Some(percent),
+        },
+        LayoutNode::Split {
+            direction,
+            children,
+            ..
+        } => LayoutNode::Split {
+            direction,
+            size: Some(percent),
+            children,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::project;
+
+    fn pane(
+        window: u32,
+        left: u32,
+        top: u32,
+        width: u32,
+        height: u32,
+        directory: &str,
+    ) -> CapturedPane {
+        CapturedPane {
+            window,
+            window_name: format!("win{}", window),
+            left,
+            top,
+            width,
+            height,
+            directory: directory.to_string(),
+            command: None,
+        }
+    }
+
+    #[test]
+    fn test_build_workspace() {
+        let project = project("/work/app");
+        // 200x50 window: two panes on top, one across the bottom
+        let mut server = pane(0, 100, 0, 100, 25, "/work/app/api");
+        server.command = Some("npm run dev".to_string());
+        let panes = vec![
+            pane(0, 0, 26, 200, 24, "/work/app"),
+            server,
+            pane(0, 0, 0, 99, 25, "/tmp"),
+            pane(1, 0, 0, 200, 50, "/work/app"),
+        ];
+
+        let workspace = build_workspace(&project, "Captured", &panes);
+        assert_eq!(workspace.name, "Captured");
+        assert_eq!(workspace.title.as_deref(), Some("win0"));
+        assert_eq!(workspace.tabs.len(), 1);
+        assert_eq!(workspace.tabs[0].panes.len(), 1);
+        assert!(crate::config::validate_workspace(&workspace).is_empty());
+
+        // Panes are numbered top to bottom, left to right
+        let directories: Vec<&str> = workspace
+            .panes
+            .iter()
+            .map(|p| p.directory.as_str())
+            .collect();
+        assert_eq!(directories, vec!["/tmp", "api", "."]);
+        assert_eq!(workspace.panes[1].command.as_deref(), Some("npm run dev"));
+
+        let tree = serde_json::to_value(workspace.layout.split.unwrap()).unwrap();
+        assert_eq!(
+            tree,
+            serde_json::json!({
+                "type": "split",
+                "direction": "vertical",
+                "children": [
+                    {
+                        "type": "split",
+                        "direction": "horizontal",
+                        "size": 52,
+                        "children": [
+                            {"type": "pane", "pane": 0, "size": 50},
+                            {"type": "pane", "pane": 1, "size": 50},
+                        ],
+                    },
+                    {"type": "pane", "pane": 2, "size": 48},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_is_shell() {
+        assert!(is_shell("zsh"));
+        assert!(is_shell("-bash"));
+        assert!(is_shell("/usr/bin/fish"));
+        assert!(!is_shell("node"));
+        assert!(!is_shell("vim"));
+    }
+}
@@ -0,0 +1,142 @@
+use super::backend::TerminalBackend;
+use super::find_in_path;
+use super::plan::{LaunchPlan, PanePlan};
+use crate::error::{CmdrError, Result};
+use std::process::Command;
+
+/// GNOME Terminal. It has no splits, so every pane of a workspace opens as
+/// a tab of its window.
+pub struct GnomeTerminal;
+
+impl GnomeTerminal {
+    /// Run `gnome-terminal` and wait for it to hand the window or tab over
+    /// to the terminal server, so that tabs open in order
+    fn run(&self, args: &[String]) -> Result<()> {
+        log::debug!("[GNOME Terminal] gnome-terminal {}", args.join(" "));
+
+        let status = Command::new("gnome-terminal")
+            .args(args)
+            .status()
+            .map_err(|e| {
+                log::error!("[GNOME Terminal] Failed to execute gnome-terminal: {}", e);
+                CmdrError::Terminal(format!("Failed to execute gnome-terminal: {}", e))
+            })?;
+
+        if !status.success() {
+            return Err(CmdrError::Terminal(format!(
+                "gnome-terminal exited with {}",
+                status
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl TerminalBackend for GnomeTerminal {
+    fn id(&self) -> &'static str {
+        "gnome"
+    }
+
+    fn name(&self) -> &'static str {
+        "GNOME Terminal"
+    }
+
+    fn is_available(&self) -> bool {
+        find_in_path("gnome-terminal").is_some()
+    }
+
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        log::info!(
+            "[GNOME Terminal] Launching workspace {}, one tab per pane",
+            plan.workspace_name
+        );
+
+        for args in invocations(plan) {
+            self.run(&args)?;
+        }
+
+        log::info!("[GNOME Terminal] Workspace launched successfully");
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        self.run(&["--window".to_string()])
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        let mut args = vec![if use_existing { "--tab" } else { "--window" }.to_string()];
+        args.extend(pane_args(pane));
+        self.run(&args)
+    }
+}
+
+/// One `gnome-terminal` invocation per pane: the first pane of each window
+/// opens the window, the rest open as tabs in it
+pub fn invocations(plan: &LaunchPlan) -> Vec<Vec<String>> {
+    let mut invocations = Vec::new();
+
+    for window in &plan.windows {
+        let panes = window
+            .tabs
+            .iter()
+            .flat_map(|tab| tab.layout.split_steps().1);
+        for (index, pane) in panes.enumerate() {
+            let mut args = vec![if index == 0 { "--window" } else { "--tab" }.to_string()];
+            args.extend(pane_args(pane));
+            invocations.push(args);
+        }
+    }
+
+    invocations
+}
+
+/// Directory of a pane, and its startup script when it has one
+fn pane_args(pane: &PanePlan) -> Vec<String> {
+    let mut args = vec![format!("--working-directory={}", pane.cwd)];
+    if pane.needs_script() {
+        args.push("--".to_string());
+        args.push("sh".to_string());
+        args.push("-c".to_string());
+        args.push(pane.startup_script());
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{layout, pane, project, workspace};
+    use crate::config::{Workspace, WorkspaceTab};
+
+    #[test]
+    fn test_invocations() {
+        let project = project("/work/app");
+        let workspace = Workspace {
+            tabs: vec![WorkspaceTab {
+                title: None,
+                window: 1,
+                layout: layout(vec![1]),
+                panes: Vec::new(),
+            }],
+            ..workspace(vec![2], vec![pane((0, 1), "api", Some("npm run dev"))])
+        };
+
+        let invocations = invocations(&LaunchPlan::build(&project, &workspace, None));
+
+        assert_eq!(
+            invocations,
+            vec![
+                vec!["--window", "--working-directory=/work/app"],
+                vec![
+                    "--tab",
+                    "--working-directory=/work/app/api",
+                    "--",
+                    "sh",
+                    "-c",
+                    "npm run dev; exec \"${SHELL:-sh}\"",
+                ],
+                vec!["--window", "--working-directory=/work/app"],
+            ]
+        );
+    }
+}
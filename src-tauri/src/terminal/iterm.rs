@@ -1,189 +1,242 @@
-use crate::config::{Project, Workspace};
-use crate::error::{CmdrError, Result};
-use std::process::Command;
-
-/// Generate AppleScript to launch a workspace in iTerm2
-pub fn launch_workspace(project: &Project, workspace: &Workspace) -> Result<()> {
-    log::info!(
-        "[iTerm] Generating AppleScript for workspace: {}",
-        workspace.name
-    );
-    log::debug!("[iTerm] Project path: {}", project.path);
-    log::debug!("[iTerm] Workspace layout: {} rows", workspace.layout.rows);
-
-    let script = generate_applescript(project, workspace);
-
-    log::debug!("[iTerm] Executing AppleScript ({} chars)", script.len());
-
-    let output = Command::new("osascript")
-        .arg("-e")
-        .arg(&script)
-        .output()
-        .map_err(|e| {
-            log::error!("[iTerm] Failed to execute AppleScript: {}", e);
-            CmdrError::Terminal(format!("Failed to execute AppleScript: {}", e))
-        })?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        log::error!("[iTerm] AppleScript error: {}", stderr);
-        return Err(CmdrError::Terminal(format!(
-            "AppleScript error: {}",
-            stderr
-        )));
+use super::backend::TerminalBackend;
+use super::plan::{LaunchPlan, PanePlan, SplitDirection};
+use super::{application_installed, osascript};
+use crate::error::Result;
+
+/// iTerm2 on macOS, driven through AppleScript
+pub struct ITerm2;
+
+impl TerminalBackend for ITerm2 {
+    fn id(&self) -> &'static str {
+        "iterm2"
     }
 
-    log::info!("[iTerm] Workspace launched successfully in iTerm2");
-    Ok(())
-}
+    fn name(&self) -> &'static str {
+        "iTerm2"
+    }
 
-fn generate_applescript(project: &Project, workspace: &Workspace) -> String {
-    let layout = &workspace.layout;
-    let panes = &workspace.panes;
+    fn is_available(&self) -> bool {
+        cfg!(target_os = "macos") && application_installed("iTerm.app")
+    }
 
-    let total_panes: u32 = layout.columns.iter().sum();
-    let num_rows = layout.columns.len();
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        log::info!(
+            "[iTerm] Generating AppleScript for workspace: {}",
+            plan.workspace_name
+        );
+        log::debug!("[iTerm] Launch plan has {} windows", plan.windows.len());
 
-    log::debug!(
-        "[iTerm] Generating script for {} panes across {} rows",
-        total_panes,
-        num_rows
-    );
-    log::debug!("[iTerm] Layout columns: {:?}", layout.columns);
+        let script = generate_applescript(plan);
+        osascript::run(&script)?;
+
+        log::info!("[iTerm] Workspace launched successfully in iTerm2");
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        osascript::run(
+            r#"
+            tell application "iTerm"
+                activate
+                create window with default profile
+            end tell
+            "#,
+        )
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        let write = write_lines(pane, "                                ");
+
+        let script = if use_existing {
+            format!(
+                r#"
+                tell application "iTerm"
+                    activate
+                    if (count of windows) > 0 then
+                        tell current window
+                            create tab with default profile
+                            tell current session
+{}                            end tell
+                        end tell
+                    else
+                        create window with default profile
+                        tell current window
+                            tell current session
+{}                            end tell
+                        end tell
+                    end if
+                end tell
+                "#,
+                write, write
+            )
+        } else {
+            format!(
+                r#"
+                tell application "iTerm"
+                    activate
+                    create window with default profile
+                    tell current window
+                        tell current session
+{}                        end tell
+                    end tell
+                end tell
+                "#,
+                write
+            )
+        };
+
+        osascript::run(&script)
+    }
+}
+
+/// `write text` statements typing the pane's shell lines
+fn write_lines(pane: &PanePlan, indent: &str) -> String {
+    pane.shell_lines()
+        .iter()
+        .map(|line| {
+            format!(
+                "{}write text \"{}\"\n",
+                indent,
+                osascript::escape_string(line)
+            )
+        })
+        .collect()
+}
+
+fn generate_applescript(plan: &LaunchPlan) -> String {
+    log::debug!("[iTerm] Generating script for {} panes", plan.panes().len());
 
     let mut script = String::new();
 
-    // Start iTerm2 and create a new window
     script.push_str(
         r#"
 tell application "iTerm"
     activate
-
-    -- Create a new window
-    create window with default profile
-
-    tell current window
-        set theTab to current tab
 "#,
     );
 
     // Strategy: Use AppleScript variables to track session references
     // This is more reliable than assuming session numbers are sequential
     //
-    // 1. Store reference to first session as sess_0_0
-    // 2. Create all rows by splitting first session horizontally, storing refs
-    // 3. For each row, split vertically to create columns, storing refs
-    // 4. Configure each session using the stored variable references
-
-    // Helper to generate variable name for a session at (row, col)
-    let sess_var = |row: usize, col: usize| format!("sess_{}_{}", row, col);
+    // 1. Store reference to the tab's first session as slot 0
+    // 2. Perform the plan's split steps in order, storing each new session
+    // 3. Configure each session using the stored variable references
 
-    // Store reference to the first session (row 0, col 0)
-    script.push_str(&format!(
-        r#"
-        -- Store reference to first session
-        set {} to current session of theTab
-"#,
-        sess_var(0, 0)
-    ));
-
-    // Step 1: Create all rows by splitting horizontally
-    // Each new row is created by splitting the previous row's first pane
-    // This ensures rows are added at the bottom in correct order
-    for row_idx in 1..num_rows {
-        let prev_row = row_idx - 1;
+    for (window_idx, window) in plan.windows.iter().enumerate() {
         script.push_str(&format!(
             r#"
-        -- Create row {} by splitting row {} horizontally
-        tell {}
-            set {} to (split horizontally with default profile)
-        end tell
+    -- Create window {}
+    create window with default profile
+
+    tell current window
 "#,
-            row_idx,
-            prev_row,
-            sess_var(prev_row, 0),
-            sess_var(row_idx, 0)
+            window_idx
         ));
-    }
 
-    // Step 2: Create columns in each row by splitting vertically
-    // We split the rightmost pane in each row to add columns to the right
-    for row_idx in 0..num_rows {
-        let cols_in_row = layout.columns[row_idx] as usize;
+        for (tab_idx, tab) in window.tabs.iter().enumerate() {
+            // Helper to generate variable name for a session slot in this tab
+            let sess_var = |slot: usize| format!("sess_{}_{}_{}", window_idx, tab_idx, slot);
+
+            if tab_idx > 0 {
+                script.push_str(
+                    r#"
+        create tab with default profile
+"#,
+                );
+            }
 
-        for col_idx in 1..cols_in_row {
-            let prev_col = col_idx - 1;
             script.push_str(&format!(
                 r#"
-        -- Create row {} col {} by splitting vertically
-        tell {}
-            set {} to (split vertically with default profile)
-        end tell
+        -- Store reference to first session of tab {}
+        set theTab to current tab
+        set {} to current session of theTab
+        set tabColumns to columns of {}
+        set tabRows to rows of {}
 "#,
-                row_idx,
-                col_idx,
-                sess_var(row_idx, prev_col),
-                sess_var(row_idx, col_idx)
+                tab_idx,
+                sess_var(0),
+                sess_var(0),
+                sess_var(0)
             ));
-        }
-    }
 
-    // Small delay to ensure all sessions are ready
-    script.push_str(
-        r#"
-        -- Small delay to ensure all sessions are ready
-        delay 0.3
+            let (steps, panes) = tab.layout.split_steps();
+
+            // iTerm names splits after the divider: "split vertically"
+            // puts the new session beside the old one
+            for step in &steps {
+                let split = match step.direction {
+                    SplitDirection::Horizontal => "vertically",
+                    SplitDirection::Vertical => "horizontally",
+                };
+                script.push_str(&format!(
+                    r#"
+        tell {}
+            set {} to (split {} with default profile)
+        end tell
 "#,
-    );
-
-    // Configure each session with its directory and command
-    for row_idx in 0..num_rows {
-        let cols_in_row = layout.columns[row_idx] as usize;
-
-        for col_idx in 0..cols_in_row {
-            // Find pane with this position
-            let pane = panes
-                .iter()
-                .find(|p| p.position.0 as usize == row_idx && p.position.1 as usize == col_idx);
-
-            let dir = if let Some(p) = pane {
-                resolve_directory(&project.path, &p.directory)
-            } else {
-                project.path.clone()
-            };
+                    sess_var(step.target),
+                    sess_var(step.pane),
+                    split
+                ));
+            }
 
-            script.push_str(&format!(
+            // Small delay to ensure all sessions are ready
+            script.push_str(
                 r#"
-        -- Configure session at row {}, col {}
-        tell {}
-            write text "cd '{}'"
+        delay 0.3
 "#,
-                row_idx,
-                col_idx,
-                sess_var(row_idx, col_idx),
-                escape_applescript_string(&dir)
-            ));
+            );
 
-            if let Some(p) = pane {
-                if let Some(cmd) = &p.command {
+            // iTerm always splits a session in half, so size each session
+            // to its share of the tab once all of them exist
+            if panes.len() > 1 {
+                for (slot, (width, height)) in tab.layout.pane_extents().into_iter().enumerate() {
                     script.push_str(&format!(
-                        r#"            write text "{}"
+                        r#"
+        tell {}
+            set columns to (tabColumns * {} div 100)
+            set rows to (tabRows * {} div 100)
+        end tell
 "#,
-                        escape_applescript_string(cmd)
+                        sess_var(slot),
+                        width,
+                        height
                     ));
                 }
             }
 
-            script.push_str(
-                r#"        end tell
+            // Configure each session with its environment, directory and commands
+            for (slot, pane) in panes.iter().enumerate() {
+                script.push_str(&format!(
+                    r#"
+        tell {}
+{}"#,
+                    sess_var(slot),
+                    write_lines(pane, "            ")
+                ));
+                if slot == 0 {
+                    if let Some(title) = &tab.title {
+                        script.push_str(&format!(
+                            "            set name to \"{}\"\n",
+                            osascript::escape_string(title)
+                        ));
+                    }
+                }
+                script.push_str(
+                    r#"        end tell
 "#,
-            );
+                );
+            }
         }
+
+        script.push_str(
+            r#"    end tell
+"#,
+        );
     }
 
     script.push_str(
-        r#"    end tell
-end tell
+        r#"end tell
 "#,
     );
 
@@ -192,51 +245,82 @@ end tell
     script
 }
 
-fn resolve_directory(project_path: &str, pane_dir: &str) -> String {
-    if pane_dir == "." || pane_dir.is_empty() {
-        project_path.to_string()
-    } else if pane_dir.starts_with('/') {
-        pane_dir.to_string()
-    } else if pane_dir.starts_with("./") {
-        format!("{}/{}", project_path, &pane_dir[2..])
-    } else {
-        format!("{}/{}", project_path, pane_dir)
-    }
-}
-
-fn escape_applescript_string(s: &str) -> String {
-    s.replace('\\', "\\\\").replace('"', "\\\"")
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::fixtures::{self, environment, layout, project, workspace};
+    use crate::config::{LayoutNode, Pane, WorkspaceTab};
     use std::collections::HashMap;
 
     #[test]
-    fn test_resolve_directory() {
-        assert_eq!(
-            resolve_directory("/home/user/project", "."),
-            "/home/user/project"
-        );
-        assert_eq!(
-            resolve_directory("/home/user/project", "./src"),
-            "/home/user/project/src"
-        );
-        assert_eq!(
-            resolve_directory("/home/user/project", "backend"),
-            "/home/user/project/backend"
-        );
+    fn test_generate_applescript_grid() {
+        let project = project("/work/app");
+        let api = Pane {
+            environment_variables: Some(HashMap::from([("PORT".to_string(), "4000".to_string())])),
+            ..fixtures::pane((0, 1), "api", Some("npm run \"dev\""))
+        };
+        let workspace = workspace(vec![2, 1], vec![api]);
+        let environment = environment("dev", &[("API_KEY", "it's")]);
+
+        let plan = LaunchPlan::build(&project, &workspace, Some(&environment));
+        let script = generate_applescript(&plan);
+
+        assert_eq!(script.matches("split horizontally").count(), 1);
+        assert_eq!(script.matches("split vertically").count(), 1);
+        assert!(script.contains("set sess_0_0_1 to (split horizontally"));
+        assert!(script.contains("set sess_0_0_2 to (split vertically"));
+        assert!(script.contains("write text \"cd '/work/app/api'\""));
+        assert!(script.contains("write text \"npm run \\\"dev\\\"\""));
+        // Every session gets the environment, panes add their own variables
         assert_eq!(
-            resolve_directory("/home/user/project", "/absolute/path"),
-            "/absolute/path"
+            script
+                .matches("write text \"export API_KEY='it'\\\\''s'")
+                .count(),
+            3
         );
+        assert!(script.contains("write text \"export API_KEY='it'\\\\''s' PORT='4000'\""));
+        // The first row is split in two, the second row spans the width
+        assert!(script.contains("tell sess_0_0_0\n            set columns to (tabColumns * 50 div 100)\n            set rows to (tabRows * 50 div 100)"));
+        assert!(script
+            .contains("tell sess_0_0_1\n            set columns to (tabColumns * 100 div 100)"));
     }
 
     #[test]
-    fn test_escape_applescript() {
-        assert_eq!(escape_applescript_string("hello"), "hello");
-        assert_eq!(escape_applescript_string("he\"llo"), "he\\\"llo");
-        assert_eq!(escape_applescript_string("he\\llo"), "he\\\\llo");
+    fn test_generate_applescript_split_tree() {
+        let project = project("/work/app");
+        let leaf = |pane: usize, size: Option<u32>| LayoutNode::Pane {
+            pane: Some(pane),
+            size,
+        };
+        let panes = ["vim", "tail -f log"]
+            .iter()
+            .map(|command| fixtures::pane((0, 0), ".", Some(command)))
+            .collect();
+        let mut workspace = workspace(Vec::new(), panes);
+        workspace.layout.split = Some(LayoutNode::Split {
+            direction: SplitDirection::Vertical,
+            size: None,
+            children: vec![leaf(0, Some(70)), leaf(1, None)],
+        });
+        workspace.tabs = vec![WorkspaceTab {
+            title: Some("logs \"tail\"".to_string()),
+            window: 1,
+            layout: layout(vec![1]),
+            panes: Vec::new(),
+        }];
+
+        let script = generate_applescript(&LaunchPlan::build(&project, &workspace, None));
+
+        assert!(script.contains("set sess_0_0_1 to (split horizontally"));
+        assert!(script.contains("set rows to (tabRows * 70 div 100)"));
+        assert!(script.contains("set rows to (tabRows * 30 div 100)"));
+        assert!(script.contains("write text \"tail -f log\""));
+        // The extra tab opens in a second window with its title
+        assert_eq!(
+            script.matches("create window with default profile").count(),
+            2
+        );
+        assert!(script.contains("set sess_1_0_0 to current session of theTab"));
+        assert!(script.contains("set name to \"logs \\\"tail\\\"\""));
     }
 }
@@ -0,0 +1,240 @@
+use super::backend::TerminalBackend;
+use super::plan::{LaunchPlan, PanePlan, SplitDirection};
+use super::{application_installed, find_in_path, process, shell, write_layout_file};
+use crate::error::{CmdrError, Result};
+use std::process::Command;
+
+/// Where the kitty binary lives inside its macOS application bundle
+const MACOS_BINARY: &str = "/Applications/kitty.app/Contents/MacOS/kitty";
+
+/// kitty, started from a generated session file. Commands go to a running
+/// kitty over remote control when one is reachable.
+pub struct Kitty;
+
+impl Kitty {
+    fn binary(&self) -> String {
+        if find_in_path("kitty").is_none() && cfg!(target_os = "macos") {
+            MACOS_BINARY.to_string()
+        } else {
+            "kitty".to_string()
+        }
+    }
+
+    /// Start a new kitty instance, optionally with a session file, and
+    /// return its process id
+    fn spawn(&self, args: &[&str]) -> Result<u32> {
+        let binary = self.binary();
+        log::info!("[kitty] Starting {} {}", binary, args.join(" "));
+
+        let child = Command::new(&binary).args(args).spawn().map_err(|e| {
+            log::error!("[kitty] Failed to start kitty: {}", e);
+            CmdrError::Terminal(format!("Failed to start kitty: {}", e))
+        })?;
+
+        Ok(child.id())
+    }
+
+    /// Open a tab in the kitty this process runs in through `kitty @`.
+    /// Only possible when kitty exported its remote control socket.
+    fn launch_remote(&self, pane: &PanePlan) -> Result<()> {
+        let socket = std::env::var("KITTY_LISTEN_ON").map_err(|_| {
+            CmdrError::Terminal("kitty remote control is not available".to_string())
+        })?;
+
+        let mut args = vec![
+            "@".to_string(),
+            "--to".to_string(),
+            socket,
+            "launch".to_string(),
+            "--type=tab".to_string(),
+        ];
+        args.extend(launch_args(pane));
+        log::debug!("[kitty] kitty {}", args.join(" "));
+
+        let output = Command::new(self.binary())
+            .args(&args)
+            .output()
+            .map_err(|e| CmdrError::Terminal(format!("Failed to execute kitty: {}", e)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(CmdrError::Terminal(format!(
+                "kitty error: {}",
+                stderr.trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl TerminalBackend for Kitty {
+    fn id(&self) -> &'static str {
+        "kitty"
+    }
+
+    fn name(&self) -> &'static str {
+        "kitty"
+    }
+
+    fn is_available(&self) -> bool {
+        find_in_path("kitty").is_some()
+            || (cfg!(target_os = "macos") && application_installed("kitty.app"))
+    }
+
+    fn is_running(&self) -> bool {
+        std::env::var_os("KITTY_LISTEN_ON").is_some()
+    }
+
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        log::info!(
+            "[kitty] Generating session for workspace: {}",
+            plan.workspace_name
+        );
+
+        let session = generate_session(plan);
+        let path = write_layout_file(&format!("{}.kitty-session", plan.session_name), &session)?;
+        let pid = self.spawn(&["--session", &path.to_string_lossy()])?;
+        process::track(&plan.session_name, pid);
+
+        log::info!("[kitty] Workspace launched successfully in kitty");
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        self.spawn(&[]).map(|_| ())
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        if use_existing {
+            match self.launch_remote(pane) {
+                Ok(()) => return Ok(()),
+                Err(e) => log::warn!("[kitty] {}, starting a new window instead", e),
+            }
+        }
+
+        let mut session = String::from("layout splits\n");
+        session.push_str(&launch_line(pane, &[]));
+        let path = write_layout_file("cmdr-run.kitty-session", &session)?;
+        self.spawn(&["--session", &path.to_string_lossy()])
+            .map(|_| ())
+    }
+}
+
+/// Generate a kitty session file for a launch plan. Every tab uses the
+/// splits layout; each pane is tagged with a user variable so that later
+/// splits can name the pane they split with `--next-to`.
+pub fn generate_session(plan: &LaunchPlan) -> String {
+    let mut session = format!("# cmdr workspace: {}\n", plan.workspace_name);
+
+    for (window_idx, window) in plan.windows.iter().enumerate() {
+        if window_idx > 0 {
+            session.push_str("\nnew_os_window\n");
+        }
+
+        for (tab_idx, tab) in window.tabs.iter().enumerate() {
+            let pane_id = |slot: usize| format!("w{}t{}p{}", window_idx, tab_idx, slot);
+            let (steps, panes) = tab.layout.split_steps();
+
+            session.push_str("\nnew_tab");
+            if let Some(title) = &tab.title {
+                session.push(' ');
+                session.push_str(title);
+            }
+            session.push_str("\nlayout splits\n");
+
+            session.push_str(&launch_line(
+                panes[0],
+                &[format!("--var=cmdr_pane={}", pane_id(0))],
+            ));
+            for step in &steps {
+                let location = match step.direction {
+                    SplitDirection::Horizontal => "--location=vsplit",
+                    SplitDirection::Vertical => "--location=hsplit",
+                };
+                let args = [
+                    location.to_string(),
+                    format!("--bias={}", step.percent),
+                    shell::quote(&format!(
+                        "--next-to=var:cmdr_pane=^{}$",
+                        pane_id(step.target)
+                    )),
+                    format!("--var=cmdr_pane={}", pane_id(step.pane)),
+                ];
+                session.push_str(&launch_line(panes[step.pane], &args));
+            }
+        }
+    }
+
+    log::debug!("[kitty] Generated session:\n{}", session);
+    session
+}
+
+/// A session `launch` line for a pane, with `extra` options before the
+/// pane's own
+fn launch_line(pane: &PanePlan, extra: &[String]) -> String {
+    let mut args = vec!["launch".to_string()];
+    args.extend(extra.iter().cloned());
+    args.extend(launch_args(pane).iter().map(|arg| shell::quote_arg(arg)));
+    args.join(" ") + "\n"
+}
+
+/// Options and program for `launch`: directory, variables, and the
+/// commands followed by the user's shell when there are any
+fn launch_args(pane: &PanePlan) -> Vec<String> {
+    let mut args = vec![format!("--cwd={}", pane.cwd)];
+    for (name, value) in &pane.env {
+        args.push(format!("--env={}={}", name, value));
+    }
+    if !pane.commands.is_empty() {
+        args.push("sh".to_string());
+        args.push("-c".to_string());
+        args.push(pane.command_script());
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{environment, layout, pane, project, workspace};
+    use crate::config::{Pane, Workspace, WorkspaceTab};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_generate_session_golden() {
+        let project = project("/work/my app");
+        let api = Pane {
+            environment_variables: Some(HashMap::from([("PORT".to_string(), "4000".to_string())])),
+            ..pane((0, 1), "api", Some("npm run dev"))
+        };
+        let workspace = Workspace {
+            title: Some("servers".to_string()),
+            tabs: vec![WorkspaceTab {
+                title: None,
+                window: 1,
+                layout: layout(vec![1]),
+                panes: Vec::new(),
+            }],
+            ..workspace(vec![2, 1], vec![api])
+        };
+        let environment = environment("dev", &[("STAGE", "it's dev")]);
+
+        let session =
+            generate_session(&LaunchPlan::build(&project, &workspace, Some(&environment)));
+
+        let expected = r#"# cmdr workspace: Dev
+
+new_tab servers
+layout splits
+launch --var=cmdr_pane=w0t0p0 '--cwd=/work/my app' '--env=STAGE=it'\''s dev'
+launch --location=hsplit --bias=50 '--next-to=var:cmdr_pane=^w0t0p0$' --var=cmdr_pane=w0t0p1 '--cwd=/work/my app' '--env=STAGE=it'\''s dev'
+launch --location=vsplit --bias=50 '--next-to=var:cmdr_pane=^w0t0p0$' --var=cmdr_pane=w0t0p2 '--cwd=/work/my app/api' --env=PORT=4000 '--env=STAGE=it'\''s dev' sh -c 'npm run dev; exec "${SHELL:-sh}"'
+
+new_os_window
+
+new_tab
+layout splits
+launch --var=cmdr_pane=w1t0p0 '--cwd=/work/my app' '--env=STAGE=it'\''s dev'
+"#;
+        assert_eq!(session, expected);
+    }
+}
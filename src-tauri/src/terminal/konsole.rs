@@ -0,0 +1,139 @@
+use super::backend::TerminalBackend;
+use super::plan::{LaunchPlan, PanePlan, WindowPlan};
+use super::{find_in_path, process, shell, write_layout_file};
+use crate::error::{CmdrError, Result};
+use std::process::Command;
+
+/// Konsole, started from a generated tabs file (`--tabs-from-file`). Every
+/// pane becomes a tab, since tabs files cannot describe splits.
+pub struct Konsole;
+
+impl Konsole {
+    /// Start konsole and return its process id
+    fn spawn(&self, args: &[String]) -> Result<u32> {
+        log::info!("[Konsole] Starting konsole {}", args.join(" "));
+
+        let child = Command::new("konsole").args(args).spawn().map_err(|e| {
+            log::error!("[Konsole] Failed to start konsole: {}", e);
+            CmdrError::Terminal(format!("Failed to start konsole: {}", e))
+        })?;
+
+        Ok(child.id())
+    }
+}
+
+impl TerminalBackend for Konsole {
+    fn id(&self) -> &'static str {
+        "konsole"
+    }
+
+    fn name(&self) -> &'static str {
+        "Konsole"
+    }
+
+    fn is_available(&self) -> bool {
+        find_in_path("konsole").is_some()
+    }
+
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        log::info!(
+            "[Konsole] Generating tabs for workspace: {}",
+            plan.workspace_name
+        );
+
+        // One Konsole window per plan window. Each is a process of its own,
+        // so that stopping the workspace cannot take down windows of a
+        // running Konsole that cmdr did not open.
+        for (index, window) in plan.windows.iter().enumerate() {
+            let tabs = generate_tabs_file(window, &plan.workspace_name);
+            let file_name = format!("{}-{}.konsole-tabs", plan.session_name, index);
+            let path = write_layout_file(&file_name, &tabs)?;
+            let pid = self.spawn(&[
+                "--separate".to_string(),
+                "--tabs-from-file".to_string(),
+                path.to_string_lossy().to_string(),
+            ])?;
+            process::track(&plan.session_name, pid);
+        }
+
+        log::info!("[Konsole] Workspace launched successfully");
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        self.spawn(&[]).map(|_| ())
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        let mut args = Vec::new();
+        if use_existing {
+            args.push("--new-tab".to_string());
+        }
+        args.push("--workdir".to_string());
+        args.push(pane.cwd.clone());
+        args.push("-e".to_string());
+        args.push("sh".to_string());
+        args.push("-c".to_string());
+        args.push(pane.startup_script());
+        self.spawn(&args).map(|_| ())
+    }
+}
+
+/// Konsole tabs file for one window: a line per pane, titled after its tab.
+/// Panes after the first in a tab are numbered.
+pub fn generate_tabs_file(window: &WindowPlan, workspace_name: &str) -> String {
+    let mut tabs = String::new();
+
+    for tab in &window.tabs {
+        let title = tab.title.as_deref().unwrap_or(workspace_name);
+        let (_, panes) = tab.layout.split_steps();
+
+        for (index, pane) in panes.iter().enumerate() {
+            let mut fields = vec![
+                match index {
+                    0 => format!("title: {}", title),
+                    _ => format!("title: {} ({})", title, index + 1),
+                },
+                format!("workdir: {}", pane.cwd),
+            ];
+            if pane.needs_script() {
+                fields.push(format!(
+                    "command: sh -c {}",
+                    shell::quote(&pane.startup_script())
+                ));
+            }
+            tabs.push_str(&fields.join(";; "));
+            tabs.push('\n');
+        }
+    }
+
+    log::debug!("[Konsole] Generated tabs file:\n{}", tabs);
+    tabs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{environment, pane, project, workspace};
+    use crate::config::Workspace;
+
+    #[test]
+    fn test_generate_tabs_file_golden() {
+        let project = project("/work/app");
+        let workspace = Workspace {
+            title: Some("servers".to_string()),
+            ..workspace(vec![2], vec![pane((0, 1), "api", Some("npm run dev"))])
+        };
+        let environment = environment("dev", &[("STAGE", "it's dev")]);
+
+        let plan = LaunchPlan::build(&project, &workspace, Some(&environment));
+
+        let expected = r#"title: servers;; workdir: /work/app;; command: sh -c 'export STAGE='\''it'\''\'\'''\''s dev'\''; exec "${SHELL:-sh}"'
+title: servers (2);; workdir: /work/app/api;; command: sh -c 'export STAGE='\''it'\''\'\'''\''s dev'\''; npm run dev; exec "${SHELL:-sh}"'
+"#;
+        assert_eq!(
+            generate_tabs_file(&plan.windows[0], &plan.workspace_name),
+            expected
+        );
+    }
+}
@@ -1,217 +1,925 @@
+mod apple_terminal;
+mod backend;
+mod capture;
+mod gnome;
 mod iterm;
+mod kitty;
+mod konsole;
+mod osascript;
+mod params;
+mod plan;
+mod process;
+mod shell;
+mod tilix;
+mod tmux;
+mod wezterm;
+mod windows_terminal;
+mod zellij;
 
+pub use backend::TerminalBackend;
+pub use plan::{LaunchPlan, PanePlan};
+
+use crate::config::{
+    AppSettings, Command, CommandParameter, Environment, PipelineStep, Project, Workspace,
+};
 use crate::error::{CmdrError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// All terminal backends cmdr knows how to drive
+fn backends() -> Vec<Box<dyn TerminalBackend>> {
+    vec![
+        Box::new(iterm::ITerm2),
+        Box::new(apple_terminal::AppleTerminal),
+        Box::new(tmux::Tmux::new()),
+        Box::new(zellij::Zellij),
+        Box::new(kitty::Kitty),
+        Box::new(wezterm::WezTerm),
+        Box::new(gnome::GnomeTerminal),
+        Box::new(konsole::Konsole),
+        Box::new(tilix::Tilix),
+        Box::new(windows_terminal::WindowsTerminal),
+    ]
+}
+
+/// Look up a backend by its `default_terminal` identifier
+pub fn backend_for(id: &str) -> Option<Box<dyn TerminalBackend>> {
+    backends().into_iter().find(|b| b.id() == id)
+}
+
+/// Backend used when the configured one is unknown or unavailable
+fn platform_default() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "iterm2"
+    } else {
+        "tmux"
+    }
+}
+
+/// Terminals to try, in order: the default one, the configured fallbacks,
+/// then the platform default
+fn candidate_terminals(settings: &AppSettings) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    let configured =
+        std::iter::once(&settings.default_terminal).chain(&settings.fallback_terminals);
+    for id in configured.map(|id| id.as_str()).chain([platform_default()]) {
+        if !candidates.iter().any(|c| c == id) {
+            candidates.push(id.to_string());
+        }
+    }
+    candidates
+}
 
+/// Resolve the backend selected in settings, going down the fallback list
+/// when it is unknown or not installed
+fn backend_from_settings(settings: &AppSettings) -> Result<Box<dyn TerminalBackend>> {
+    let candidates = candidate_terminals(settings);
+
+    for id in &candidates {
+        match backend_for(id) {
+            Some(backend) if backend.is_available() => {
+                if *id != settings.default_terminal {
+                    log::warn!(
+                        "[Terminal] {} is not available, falling back to {}",
+                        settings.default_terminal,
+                        backend.name()
+                    );
+                }
+                return Ok(backend);
+            }
+            Some(backend) => log::debug!("[Terminal] {} is not available", backend.name()),
+            None => log::warn!("[Terminal] Unknown terminal '{}'", id),
+        }
+    }
+
+    Err(CmdrError::Terminal(format!(
+        "None of the configured terminals are available on this system: {}",
+        candidates.join(", ")
+    )))
+}
+
+/// What cmdr found out about one terminal backend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalInfo {
+    pub id: String,
+    pub name: String,
+    /// Installed and usable on this machine
+    pub available: bool,
+    /// Has a server running, for multiplexers and terminals with a
+    /// control socket
+    pub running: bool,
+}
+
+/// Report which terminals are installed and which have a server running
 #[tauri::command]
-pub fn launch_workspace(project_id: String, workspace_id: String) -> Result<()> {
-    log::info!("[Terminal] Launching workspace {} for project {}", workspace_id, project_id);
+pub fn detect_terminals() -> Vec<TerminalInfo> {
+    log::info!("[Terminal] Detecting terminals");
+
+    backends()
+        .iter()
+        .map(|backend| {
+            let available = backend.is_available();
+            TerminalInfo {
+                id: backend.id().to_string(),
+                name: backend.name().to_string(),
+                available,
+                running: available && backend.is_running(),
+            }
+        })
+        .collect()
+}
+
+/// Look up the environment to launch with: the one asked for, or the
+/// workspace's default when none was given
+fn select_environment<'a>(
+    project: &'a Project,
+    requested: Option<&str>,
+    workspace: Option<&Workspace>,
+) -> Result<Option<&'a Environment>> {
+    let name = match requested.or_else(|| workspace.and_then(|w| w.default_environment.as_deref()))
+    {
+        Some(name) if !name.is_empty() => name,
+        _ => return Ok(None),
+    };
+
+    let environment = project.environment(name).ok_or_else(|| {
+        log::error!(
+            "[Terminal] Environment not found in project {}: {}",
+            project.name,
+            name
+        );
+        CmdrError::EnvironmentNotFound(name.to_string())
+    })?;
+    log::info!("[Terminal] Using environment: {}", environment.name);
+    Ok(Some(environment))
+}
 
+/// Load a project and one of its workspaces
+fn load_workspace(project_id: &str, workspace_id: &str) -> Result<(Project, Workspace)> {
     let projects = crate::config::load_projects()?;
     let project = projects
         .into_iter()
         .find(|p| p.id == project_id)
-        .ok_or_else(|| CmdrError::ProjectNotFound(project_id.clone()))?;
+        .ok_or_else(|| CmdrError::ProjectNotFound(project_id.to_string()))?;
 
     let workspace = project
         .workspaces
         .iter()
         .find(|w| w.id == workspace_id)
+        .cloned()
         .ok_or_else(|| CmdrError::ProjectNotFound(format!("workspace:{}", workspace_id)))?;
 
-    log::info!("[Terminal] Found workspace: {} with {} panes", workspace.name, workspace.panes.len());
+    Ok((project, workspace))
+}
 
-    // Launch using iTerm2 (macOS only for MVP)
-    #[cfg(target_os = "macos")]
-    {
-        iterm::launch_workspace(&project, workspace)?;
-        log::info!("[Terminal] Workspace launched successfully");
-    }
+/// Load a project's workspace and work out how it will be launched
+fn workspace_plan(
+    project_id: &str,
+    workspace_id: &str,
+    environment: Option<&str>,
+) -> Result<LaunchPlan> {
+    let (project, workspace) = load_workspace(project_id, workspace_id)?;
 
-    #[cfg(not(target_os = "macos"))]
-    {
-        return Err(CmdrError::Terminal(
-            "Terminal automation not yet supported on this platform".to_string(),
-        ));
+    log::info!(
+        "[Terminal] Found workspace: {} with {} panes",
+        workspace.name,
+        workspace.panes.len()
+    );
+
+    let issues = crate::config::validate_workspace(&workspace);
+    if !issues.is_empty() {
+        log::error!(
+            "[Terminal] Workspace {} has {} layout issues",
+            workspace.name,
+            issues.len()
+        );
+        return Err(CmdrError::Validation(issues));
     }
 
-    Ok(())
+    let environment = select_environment(&project, environment, Some(&workspace))?;
+    Ok(LaunchPlan::build(&project, &workspace, environment))
 }
 
+/// What to do when a workspace's session is already running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExistingSession {
+    /// Bring the running session to the foreground
+    Attach,
+    /// End the running session and launch the workspace again
+    Recreate,
+    /// Leave the running session alone and launch nothing
+    Cancel,
+}
+
+/// A workspace whose session is running in a multiplexer
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningWorkspace {
+    pub project_id: String,
+    pub project_name: String,
+    pub workspace_id: String,
+    pub workspace_name: String,
+    pub session_name: String,
+    /// Id of the backend the session runs in
+    pub terminal: String,
+}
+
+/// Launch a workspace. When its session is already running, `existing`
+/// decides what happens; without it the launch fails rather than start
+/// every pane a second time.
 #[tauri::command]
-pub fn open_terminal() -> Result<()> {
-    log::info!("[Terminal] Opening new terminal window");
+pub fn launch_workspace(
+    project_id: String,
+    workspace_id: String,
+    environment: Option<String>,
+    existing: Option<ExistingSession>,
+) -> Result<()> {
+    log::info!(
+        "[Terminal] Launching workspace {} for project {}",
+        workspace_id,
+        project_id
+    );
 
-    #[cfg(target_os = "macos")]
-    {
-        // Load settings to check which terminal to use
-        let settings = crate::config::load_settings()?;
-        log::info!("[Terminal] Using terminal: {}", settings.default_terminal);
-
-        let script = match settings.default_terminal.as_str() {
-            "terminal" => {
-                r#"
-                tell application "Terminal"
-                    activate
-                    do script ""
-                end tell
-                "#
-            }
-            _ => {
-                // Default to iTerm2
-                r#"
-                tell application "iTerm"
-                    activate
-                    create window with default profile
-                end tell
-                "#
-            }
-        };
+    let plan = workspace_plan(&project_id, &workspace_id, environment.as_deref())?;
 
-        let output = std::process::Command::new("osascript")
-            .arg("-e")
-            .arg(script)
-            .output()
-            .map_err(|e| CmdrError::Terminal(format!("Failed to execute AppleScript: {}", e)))?;
+    let settings = crate::config::load_settings()?;
+    let backend = backend_from_settings(&settings)?;
+    log::info!("[Terminal] Using terminal: {}", backend.name());
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            log::error!("[Terminal] AppleScript error: {}", stderr);
-            return Err(CmdrError::Terminal(format!("AppleScript error: {}", stderr)));
+    let session = &plan.session_name;
+    if backend.running_sessions().contains(session) {
+        log::info!(
+            "[Terminal] Session {} is already running, existing: {:?}",
+            session,
+            existing
+        );
+        match existing {
+            None => {
+                return Err(CmdrError::Terminal(format!(
+                    "Workspace {} is already running in session {}",
+                    plan.workspace_name, session
+                )))
+            }
+            Some(ExistingSession::Cancel) => return Ok(()),
+            Some(ExistingSession::Attach) => return backend.attach_session(session),
+            Some(ExistingSession::Recreate) => backend.kill_session(session)?,
         }
+    }
+
+    backend.launch_workspace(&plan)?;
+    log::info!("[Terminal] Workspace launched successfully");
+
+    Ok(())
+}
+
+/// Stop a launched workspace: end the programs running in its panes, then
+/// close its multiplexer sessions and the terminal windows cmdr started for
+/// it. Programs get a graceful signal first and are killed if they are
+/// still running after a grace period.
+///
+/// Runs off the main thread, since waiting out the grace period would
+/// freeze the window.
+#[tauri::command(async)]
+pub fn stop_workspace(project_id: String, workspace_id: String) -> Result<()> {
+    log::info!(
+        "[Terminal] Stopping workspace {} for project {}",
+        workspace_id,
+        project_id
+    );
 
-        log::info!("[Terminal] Terminal opened successfully");
+    let (project, workspace) = load_workspace(&project_id, &workspace_id)?;
+    let session = plan::session_name(&project, &workspace);
+    let mut stopped = false;
+
+    for backend in backends().iter().filter(|b| b.is_available()) {
+        if !backend.running_sessions().contains(&session) {
+            continue;
+        }
+        log::info!(
+            "[Terminal] Stopping session {} in {}",
+            session,
+            backend.name()
+        );
+        // Pane shells ignore the graceful signal, so only their children get it
+        let shells = backend.session_pids(&session);
+        process::terminate_trees(&shells, false, process::GRACE_PERIOD);
+        backend.kill_session(&session)?;
+        stopped = true;
     }
 
-    #[cfg(not(target_os = "macos"))]
-    {
-        return Err(CmdrError::Terminal(
-            "Terminal automation not yet supported on this platform".to_string(),
-        ));
+    let spawned = process::take_tracked(&session)?;
+    if !spawned.is_empty() {
+        process::terminate_trees(&spawned, true, process::GRACE_PERIOD);
+        stopped = true;
     }
 
+    if !stopped {
+        log::info!("[Terminal] Workspace {} was not running", workspace.name);
+    }
     Ok(())
 }
 
+/// Workspaces whose sessions are running in any available multiplexer
 #[tauri::command]
-pub fn run_command(project_id: String, command: String) -> Result<()> {
-    log::info!("[Terminal] Running command '{}' for project {}", command, project_id);
+pub fn list_running_workspaces() -> Result<Vec<RunningWorkspace>> {
+    let sessions: Vec<(&'static str, String)> = backends()
+        .iter()
+        .filter(|backend| backend.is_available())
+        .flat_map(|backend| {
+            let id = backend.id();
+            backend.running_sessions().into_iter().map(move |s| (id, s))
+        })
+        .collect();
+    log::debug!("[Terminal] Running sessions: {:?}", sessions);
 
     let projects = crate::config::load_projects()?;
-    let project = projects
+    Ok(running_workspaces(&projects, &sessions))
+}
+
+/// Match running sessions to the workspaces that would have created them
+fn running_workspaces(projects: &[Project], sessions: &[(&str, String)]) -> Vec<RunningWorkspace> {
+    let mut running = Vec::new();
+    for project in projects {
+        for workspace in &project.workspaces {
+            let session_name = plan::session_name(project, workspace);
+            for (terminal, _) in sessions.iter().filter(|(_, s)| *s == session_name) {
+                running.push(RunningWorkspace {
+                    project_id: project.id.clone(),
+                    project_name: project.name.clone(),
+                    workspace_id: workspace.id.clone(),
+                    workspace_name: workspace.name.clone(),
+                    session_name: session_name.clone(),
+                    terminal: terminal.to_string(),
+                });
+            }
+        }
+    }
+    running
+}
+
+/// Sessions running on the tmux server, for choosing one to import
+#[tauri::command]
+pub fn list_tmux_sessions() -> Vec<String> {
+    tmux::Tmux::new().running_sessions()
+}
+
+/// Turn a running tmux session into a new workspace of a project: every
+/// tmux window becomes a tab whose split layout, directories and running
+/// programs match the session's panes
+#[tauri::command]
+pub fn import_tmux_session(
+    project_id: String,
+    session: String,
+    name: Option<String>,
+) -> Result<Project> {
+    log::info!(
+        "[Terminal] Importing tmux session {} into project {}",
+        session,
+        project_id
+    );
+
+    let mut project = crate::config::load_projects()?
         .into_iter()
         .find(|p| p.id == project_id)
         .ok_or_else(|| CmdrError::ProjectNotFound(project_id.clone()))?;
 
-    log::info!("[Terminal] Found project at path: {}", project.path);
+    let panes = tmux::Tmux::new().capture_session(&session)?;
+    if panes.is_empty() {
+        return Err(CmdrError::Terminal(format!(
+            "tmux session {} has no panes",
+            session
+        )));
+    }
 
-    #[cfg(target_os = "macos")]
-    {
-        // Load settings to check which terminal to use
-        let settings = crate::config::load_settings()?;
-        log::info!("[Terminal] Using terminal: {}, behavior: {}", settings.default_terminal, settings.terminal_behavior);
-
-        let escaped_path = project.path.replace("'", "'\\''");
-        let escaped_command = command.replace("'", "'\\''");
-
-        let use_existing = settings.terminal_behavior == "use_existing";
-
-        let script = match settings.default_terminal.as_str() {
-            "terminal" => {
-                if use_existing {
-                    format!(
-                        r#"
-                        tell application "Terminal"
-                            activate
-                            if (count of windows) > 0 then
-                                do script "cd '{}' && {}" in front window
-                            else
-                                do script "cd '{}' && {}"
-                            end if
-                        end tell
-                        "#,
-                        escaped_path, escaped_command, escaped_path, escaped_command
-                    )
-                } else {
-                    format!(
-                        r#"
-                        tell application "Terminal"
-                            activate
-                            do script "cd '{}' && {}"
-                        end tell
-                        "#,
-                        escaped_path, escaped_command
-                    )
-                }
-            }
-            _ => {
-                // Default to iTerm2
-                if use_existing {
-                    format!(
-                        r#"
-                        tell application "iTerm"
-                            activate
-                            if (count of windows) > 0 then
-                                tell current window
-                                    create tab with default profile
-                                    tell current session
-                                        write text "cd '{}'"
-                                        write text "{}"
-                                    end tell
-                                end tell
-                            else
-                                create window with default profile
-                                tell current window
-                                    tell current session
-                                        write text "cd '{}'"
-                                        write text "{}"
-                                    end tell
-                                end tell
-                            end if
-                        end tell
-                        "#,
-                        escaped_path, escaped_command, escaped_path, escaped_command
-                    )
-                } else {
-                    format!(
-                        r#"
-                        tell application "iTerm"
-                            activate
-                            create window with default profile
-                            tell current window
-                                tell current session
-                                    write text "cd '{}'"
-                                    write text "{}"
-                                end tell
-                            end tell
-                        end tell
-                        "#,
-                        escaped_path, escaped_command
-                    )
-                }
+    let name = name
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| session.clone());
+    let workspace = capture::build_workspace(&project, &name, &panes);
+
+    let issues = crate::config::validate_workspace(&workspace);
+    if !issues.is_empty() {
+        log::error!(
+            "[Terminal] Imported workspace {} has {} layout issues",
+            workspace.name,
+            issues.len()
+        );
+        return Err(CmdrError::Validation(issues));
+    }
+
+    log::info!(
+        "[Terminal] Imported workspace {} with {} panes",
+        workspace.name,
+        panes.len()
+    );
+    project.workspaces.push(workspace);
+    crate::config::save_project(&project)?;
+    Ok(project)
+}
+
+/// The plan `launch_workspace` would follow, without opening anything
+#[tauri::command]
+pub fn preview_launch(
+    project_id: String,
+    workspace_id: String,
+    environment: Option<String>,
+) -> Result<LaunchPlan> {
+    log::info!(
+        "[Terminal] Previewing workspace {} for project {}",
+        workspace_id,
+        project_id
+    );
+    workspace_plan(&project_id, &workspace_id, environment.as_deref())
+}
+
+#[tauri::command]
+pub fn open_terminal() -> Result<()> {
+    log::info!("[Terminal] Opening new terminal window");
+
+    let settings = crate::config::load_settings()?;
+    let backend = backend_from_settings(&settings)?;
+    log::info!("[Terminal] Using terminal: {}", backend.name());
+
+    backend.open_terminal()?;
+    log::info!("[Terminal] Terminal opened successfully");
+
+    Ok(())
+}
+
+/// Run a command in the configured terminal. `command_id` picks a saved
+/// project or global command, whose parameters fill the placeholders
+/// together with `values`, and whose dependencies run first; without it
+/// `command` is run as given.
+#[tauri::command]
+pub fn run_command(
+    project_id: String,
+    command: String,
+    environment: Option<String>,
+    command_id: Option<String>,
+    values: Option<HashMap<String, String>>,
+) -> Result<()> {
+    log::info!(
+        "[Terminal] Running command '{}' for project {}",
+        command,
+        project_id
+    );
+
+    let values = values.unwrap_or_default();
+    let pane = match command_id.as_deref() {
+        Some(id) => chained_plan(&project_id, id, environment.as_deref(), &values)?,
+        None => command_plan(&project_id, &command, None, environment.as_deref(), &values)?,
+    };
+
+    let settings = crate::config::load_settings()?;
+    let backend = backend_from_settings(&settings)?;
+    log::info!(
+        "[Terminal] Using terminal: {}, behavior: {}",
+        backend.name(),
+        settings.terminal_behavior
+    );
+
+    let use_existing = settings.terminal_behavior == "use_existing";
+    backend.run_command(&pane, use_existing)?;
+    log::info!("[Terminal] Command executed successfully");
+
+    Ok(())
+}
+
+/// The values to ask for before running a command: the declared
+/// parameters and `{{input:...}}` placeholders of the command and of
+/// everything it depends on
+#[tauri::command]
+pub fn get_command_inputs(
+    project_id: String,
+    command: String,
+    command_id: Option<String>,
+) -> Result<Vec<CommandParameter>> {
+    let project = load_project(&project_id)?;
+    let Some(id) = command_id else {
+        return Ok(params::inputs(&command, &[]));
+    };
+
+    let mut inputs: Vec<CommandParameter> = Vec::new();
+    for step in saved_pipeline(&project, &id)? {
+        for input in params::inputs(&step.command.command, &step.command.parameters) {
+            if !inputs.iter().any(|i| i.name == input.name) {
+                inputs.push(input);
             }
-        };
+        }
+    }
+    Ok(inputs)
+}
+
+/// Where and with which environment a command of a project runs, with its
+/// placeholders filled in. Fails with every missing or invalid value.
+pub fn command_plan(
+    project_id: &str,
+    command: &str,
+    command_id: Option<&str>,
+    environment: Option<&str>,
+    values: &HashMap<String, String>,
+) -> Result<PanePlan> {
+    let project = load_project(project_id)?;
+    log::info!("[Terminal] Found project at path: {}", project.path);
+
+    let environment = select_environment(&project, environment, None)?;
+    let (template, parameters) = match saved_command(&project, command_id)? {
+        Some(saved) => (saved.command, saved.parameters),
+        None => (command.to_string(), Vec::new()),
+    };
+    resolve_plan(&project, environment, &template, &parameters, values)
+}
 
-        let output = std::process::Command::new("osascript")
-            .arg("-e")
-            .arg(&script)
-            .output()
-            .map_err(|e| CmdrError::Terminal(format!("Failed to execute AppleScript: {}", e)))?;
+/// A saved command and everything it depends on, in the order they can
+/// run in, each with its placeholders filled in. Every step is resolved
+/// before anything runs, so a missing value stops the whole pipeline.
+pub fn pipeline_plan(
+    project_id: &str,
+    command_id: &str,
+    environment: Option<&str>,
+    values: &HashMap<String, String>,
+) -> Result<Vec<(PipelineStep, PanePlan)>> {
+    let project = load_project(project_id)?;
+    let environment = select_environment(&project, environment, None)?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            log::error!("[Terminal] AppleScript error: {}", stderr);
-            return Err(CmdrError::Terminal(format!("AppleScript error: {}", stderr)));
+    let mut plans = Vec::new();
+    let mut issues = Vec::new();
+    for step in saved_pipeline(&project, command_id)? {
+        let command = &step.command;
+        match resolve_plan(
+            &project,
+            environment,
+            &command.command,
+            &command.parameters,
+            values,
+        ) {
+            Ok(pane) => plans.push((step, pane)),
+            Err(CmdrError::Parameters(step_issues)) => issues.extend(
+                step_issues
+                    .into_iter()
+                    .map(|issue| format!("{}: {}", command.name, issue)),
+            ),
+            Err(e) => return Err(e),
         }
+    }
 
-        log::info!("[Terminal] Command executed successfully");
+    if issues.is_empty() {
+        Ok(plans)
+    } else {
+        Err(CmdrError::Parameters(issues))
     }
+}
 
-    #[cfg(not(target_os = "macos"))]
-    {
-        return Err(CmdrError::Terminal(
-            "Terminal automation not yet supported on this platform".to_string(),
+/// The project's service commands, or just the one with `command_id`, each
+/// with the pane it runs in. A service's own environment wins over
+/// `environment`; parameters only get their defaults.
+pub fn service_plans(
+    project_id: &str,
+    command_id: Option<&str>,
+    environment: Option<&str>,
+) -> Result<Vec<(Command, PanePlan)>> {
+    let project = load_project(project_id)?;
+
+    let mut plans = Vec::new();
+    let mut issues = Vec::new();
+    let services = project
+        .commands
+        .iter()
+        .filter(|c| c.service.is_some() && command_id.is_none_or(|id| c.id == id));
+    for command in services {
+        let own = command
+            .service
+            .as_ref()
+            .and_then(|s| s.environment.as_deref());
+        let environment = select_environment(&project, own.or(environment), None)?;
+        match resolve_plan(
+            &project,
+            environment,
+            &command.command,
+            &command.parameters,
+            &HashMap::new(),
+        ) {
+            Ok(pane) => plans.push((command.clone(), pane)),
+            Err(CmdrError::Parameters(service_issues)) => issues.extend(
+                service_issues
+                    .into_iter()
+                    .map(|issue| format!("{}: {}", command.name, issue)),
+            ),
+            Err(e) => return Err(e),
+        }
+    }
+
+    if !issues.is_empty() {
+        return Err(CmdrError::Parameters(issues));
+    }
+    if plans.is_empty() {
+        return Err(CmdrError::Run(match command_id {
+            Some(id) => format!("No service with id {} in project {}", id, project.name),
+            None => format!("Project {} has no services", project.name),
+        }));
+    }
+    Ok(plans)
+}
+
+/// A saved command with its dependencies run one after the other in a
+/// single shell, stopping at the first one that fails
+fn chained_plan(
+    project_id: &str,
+    command_id: &str,
+    environment: Option<&str>,
+    values: &HashMap<String, String>,
+) -> Result<PanePlan> {
+    let mut plans = pipeline_plan(project_id, command_id, environment, values)?;
+    let chain = chain_steps(plans.iter().map(|(_, pane)| pane));
+    // Every step runs under the project path with the same environment
+    let (_, pane) = plans.pop().expect("a pipeline ends with its command");
+    Ok(PanePlan {
+        commands: vec![chain],
+        ..pane
+    })
+}
+
+/// Join the commands of pipeline steps with `&&`, each step in a subshell
+/// so that a `cd` or `export` in one does not leak into the next
+fn chain_steps<'a>(panes: impl Iterator<Item = &'a PanePlan>) -> String {
+    panes
+        .map(|pane| format!("( {} )", pane.commands.join("; ")))
+        .collect::<Vec<_>>()
+        .join(" && ")
+}
+
+fn resolve_plan(
+    project: &Project,
+    environment: Option<&Environment>,
+    template: &str,
+    parameters: &[CommandParameter],
+    values: &HashMap<String, String>,
+) -> Result<PanePlan> {
+    let branch = || git_branch(&project.path);
+    let context = params::ParameterContext {
+        project,
+        environment,
+        branch: &branch,
+    };
+    let resolved = params::resolve(template, parameters, &context, values).map_err(|issues| {
+        log::error!(
+            "[Terminal] Command '{}' has parameter issues: {:?}",
+            template,
+            issues
+        );
+        CmdrError::Parameters(issues)
+    })?;
+
+    Ok(PanePlan::for_command(project, &resolved, environment))
+}
+
+fn load_project(project_id: &str) -> Result<Project> {
+    crate::config::load_projects()?
+        .into_iter()
+        .find(|p| p.id == project_id)
+        .ok_or_else(|| CmdrError::ProjectNotFound(project_id.to_string()))
+}
+
+/// A saved command of the project, or a global command, by id
+fn saved_command(project: &Project, command_id: Option<&str>) -> Result<Option<Command>> {
+    let Some(id) = command_id else {
+        return Ok(None);
+    };
+    if let Some(command) = project.commands.iter().find(|c| c.id == id) {
+        return Ok(Some(command.clone()));
+    }
+
+    crate::config::load_settings()?
+        .global_commands
+        .into_iter()
+        .find(|c| c.id == id)
+        .map(Some)
+        .ok_or_else(|| CmdrError::ProjectNotFound(format!("command:{}", id)))
+}
+
+/// A saved command and its dependencies, see `command_pipeline`
+fn saved_pipeline(project: &Project, command_id: &str) -> Result<Vec<PipelineStep>> {
+    let settings = crate::config::load_settings()?;
+    crate::config::command_pipeline(&project.commands, &settings.global_commands, command_id)
+}
+
+/// The checked out branch of a git repository
+fn git_branch(path: &str) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["-C", path, "rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .ok()?;
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !branch.is_empty()).then_some(branch)
+}
+
+/// A project environment as a single `export` line for a shell, or `None`
+/// when it has no usable variables
+pub fn environment_exports(environment: &Environment) -> Option<String> {
+    shell::export_line(&shell::pane_environment(Some(environment), None))
+}
+
+/// Find an executable on PATH
+fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Terminal emulator used to show full-screen clients such as multiplexers:
+/// `$TERMINAL`, or the Debian-style `x-terminal-emulator` alternative
+fn terminal_emulator() -> String {
+    std::env::var("TERMINAL").unwrap_or_else(|_| "x-terminal-emulator".to_string())
+}
+
+/// Write a generated layout or session file to `~/.config/cmdr/layouts`,
+/// where it can be inspected or reused by hand
+fn write_layout_file(file_name: &str, contents: &str) -> Result<PathBuf> {
+    let dir = crate::config::get_config_dir()?.join("layouts");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(file_name);
+    log::debug!("[Terminal] Writing layout file: {:?}", path);
+    std::fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Whether a macOS application bundle is installed system-wide or for the user
+fn application_installed(bundle: &str) -> bool {
+    let system = PathBuf::from("/Applications").join(bundle);
+    let user = dirs::home_dir().map(|home| home.join("Applications").join(bundle));
+    system.exists() || user.is_some_and(|p| p.exists())
+}
+
+/// Resolve a pane directory relative to the project path, joined with the
+/// platform's separator
+fn resolve_directory(project_path: &str, pane_dir: &str) -> String {
+    match pane_dir.strip_prefix("./").unwrap_or(pane_dir) {
+        "" | "." => project_path.to_string(),
+        relative => Path::new(project_path)
+            .join(relative)
+            .to_string_lossy()
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{self, environment};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_resolve_directory() {
+        assert_eq!(
+            resolve_directory("/home/user/project", "."),
+            "/home/user/project"
+        );
+        assert_eq!(
+            resolve_directory("/home/user/project", "./src"),
+            "/home/user/project/src"
+        );
+        assert_eq!(
+            resolve_directory("/home/user/project", "backend"),
+            "/home/user/project/backend"
+        );
+        assert_eq!(
+            resolve_directory("/home/user/project", "/absolute/path"),
+            "/absolute/path"
+        );
+    }
+
+    #[test]
+    fn test_select_environment() {
+        let mut project = fixtures::project("/work/app");
+        project.environments =
+            HashMap::from([("staging".to_string(), environment("Staging", &[]))]);
+        let workspace = Workspace {
+            default_environment: Some("staging".to_string()),
+            ..fixtures::workspace(vec![1], Vec::new())
+        };
+
+        // Looked up by key or by display name
+        let env = select_environment(&project, Some("Staging"), None).unwrap();
+        assert_eq!(env.unwrap().name, "Staging");
+        // Falls back to the workspace default
+        let env = select_environment(&project, None, Some(&workspace)).unwrap();
+        assert_eq!(env.unwrap().name, "Staging");
+        assert!(select_environment(&project, None, None).unwrap().is_none());
+        assert!(matches!(
+            select_environment(&project, Some("prod"), Some(&workspace)),
+            Err(CmdrError::EnvironmentNotFound(_))
         ));
     }
 
-    Ok(())
+    #[test]
+    fn test_chain_steps() {
+        let step = |command: &str| PanePlan {
+            position: None,
+            cwd: "/work/app".to_string(),
+            env: Default::default(),
+            commands: vec![command.to_string()],
+        };
+        let chain =
+            |steps: &[&str]| chain_steps(steps.iter().map(|s| step(s)).collect::<Vec<_>>().iter());
+        assert_eq!(
+            chain(&["cd web; npm ci", "export MODE=ci", "make test"]),
+            "( cd web; npm ci ) && ( export MODE=ci ) && ( make test )"
+        );
+
+        // A failed step stops the rest of a later step too, and a `cd` stays
+        // in its own step
+        let root = std::env::temp_dir().join(format!("cmdr-chain-test-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        let run = |steps: &[&str]| {
+            std::process::Command::new("sh")
+                .args(["-c", &chain(steps)])
+                .current_dir(&root)
+                .status()
+                .unwrap()
+        };
+        let failed = run(&["false", "cd sub; touch ran"]);
+        let passed = run(&["cd sub; true", "touch ran"]);
+        let ran_in_root = root.join("ran").exists();
+        let ran_in_sub = root.join("sub").join("ran").exists();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(!failed.success());
+        assert!(passed.success());
+        assert!(ran_in_root);
+        assert!(!ran_in_sub);
+    }
+
+    #[test]
+    fn test_running_workspaces() {
+        let workspace = |id: &str, name: &str| Workspace {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..fixtures::workspace(vec![1], Vec::new())
+        };
+        let project = Project {
+            name: "My App".to_string(),
+            workspaces: vec![workspace("w1", "Dev"), workspace("w2", "Logs")],
+            ..fixtures::project("/work/app")
+        };
+        let sessions = vec![
+            ("tmux", "cmdr-my-app-logs-w2".to_string()),
+            ("tmux", "scratch".to_string()),
+            ("zellij", "cmdr-my-app-logs-w2".to_string()),
+        ];
+
+        let running = running_workspaces(&[project], &sessions);
+        let found: Vec<_> = running
+            .iter()
+            .map(|r| (r.workspace_id.as_str(), r.terminal.as_str()))
+            .collect();
+        assert_eq!(found, vec![("w2", "tmux"), ("w2", "zellij")]);
+        assert_eq!(running[0].session_name, "cmdr-my-app-logs-w2");
+    }
+
+    #[test]
+    fn test_candidate_terminals() {
+        let settings = AppSettings {
+            default_terminal: "kitty".to_string(),
+            fallback_terminals: vec![
+                "wezterm".to_string(),
+                "kitty".to_string(),
+                platform_default().to_string(),
+            ],
+            ..AppSettings::default()
+        };
+        assert_eq!(
+            candidate_terminals(&settings),
+            vec!["kitty", "wezterm", platform_default()]
+        );
+
+        let settings = AppSettings {
+            default_terminal: "konsole".to_string(),
+            ..AppSettings::default()
+        };
+        assert_eq!(
+            candidate_terminals(&settings),
+            vec!["konsole", platform_default()]
+        );
+    }
+
+    #[test]
+    fn test_backend_for() {
+        assert_eq!(backend_for("iterm2").unwrap().name(), "iTerm2");
+        assert_eq!(backend_for("terminal").unwrap().name(), "Terminal.app");
+        assert_eq!(backend_for("tmux").unwrap().name(), "tmux");
+        assert_eq!(backend_for("zellij").unwrap().name(), "zellij");
+        assert_eq!(backend_for("kitty").unwrap().name(), "kitty");
+        assert_eq!(backend_for("wezterm").unwrap().name(), "WezTerm");
+        assert_eq!(backend_for("gnome").unwrap().name(), "GNOME Terminal");
+        assert_eq!(backend_for("konsole").unwrap().name(), "Konsole");
+        assert_eq!(backend_for("tilix").unwrap().name(), "Tilix");
+        assert_eq!(
+            backend_for("windows_terminal").unwrap().name(),
+            "Windows Terminal"
+        );
+        assert!(backend_for("unknown").is_none());
+    }
 }
@@ -0,0 +1,47 @@
+use crate::error::{CmdrError, Result};
+use std::process::Command;
+
+/// Execute an AppleScript with `osascript`
+pub fn run(script: &str) -> Result<()> {
+    log::debug!(
+        "[AppleScript] Executing AppleScript ({} chars)",
+        script.len()
+    );
+
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .map_err(|e| {
+            log::error!("[AppleScript] Failed to execute AppleScript: {}", e);
+            CmdrError::Terminal(format!("Failed to execute AppleScript: {}", e))
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log::error!("[AppleScript] AppleScript error: {}", stderr);
+        return Err(CmdrError::Terminal(format!(
+            "AppleScript error: {}",
+            stderr
+        )));
+    }
+
+    Ok(())
+}
+
+/// Escape a string for use inside an AppleScript string literal
+pub fn escape_string(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_applescript() {
+        assert_eq!(escape_string("hello"), "hello");
+        assert_eq!(escape_string("he\"llo"), "he\\\"llo");
+        assert_eq!(escape_string("he\\llo"), "he\\\\llo");
+    }
+}
@@ -0,0 +1,329 @@
+use super::shell;
+use crate::config::{CommandParameter, Environment, ParameterType, Project};
+use std::collections::HashMap;
+
+/// What the placeholders of a command are filled with, besides the values
+/// given for its parameters
+pub struct ParameterContext<'a> {
+    pub project: &'a Project,
+    pub environment: Option<&'a Environment>,
+    /// Current git branch of the project; only asked for when `{{branch}}`
+    /// is used
+    pub branch: &'a dyn Fn() -> Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Placeholder<'a> {
+    /// `{{branch}}`, unless a parameter is called `branch`
+    Branch,
+    /// `{{env.NAME}}`
+    Env(&'a str),
+    /// `{{project.path}}`, `{{project.name}}` or `{{project.id}}`
+    Project(&'a str),
+    /// `{{input:Label}}`: a value asked for at run time
+    Input(&'a str),
+    /// `{{name}}`: a declared parameter
+    Parameter(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(Placeholder<'a>),
+}
+
+/// Split a command into text and `{{...}}` placeholders. Braces that are
+/// not one of ours, such as a Go template in `docker ps --format`, are kept
+/// as text.
+fn parse<'a>(template: &'a str, parameters: &[CommandParameter]) -> Vec<Segment<'a>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = after[..end].trim();
+
+        let placeholder = if name == "branch" {
+            Placeholder::Branch
+        } else if let Some(variable) = name.strip_prefix("env.") {
+            Placeholder::Env(variable)
+        } else if let Some(field) = name.strip_prefix("project.") {
+            Placeholder::Project(field)
+        } else if let Some(label) = name.strip_prefix("input:") {
+            Placeholder::Input(label.trim())
+        } else if parameters.iter().any(|p| p.name == name) {
+            Placeholder::Parameter(name)
+        } else {
+            segments.push(Segment::Text(&rest[start..start + end + 4]));
+            rest = &after[end + 2..];
+            continue;
+        };
+        segments.push(Segment::Placeholder(placeholder));
+        rest = &after[end + 2..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    segments
+}
+
+/// The values a command needs from the user before it can run: its
+/// declared parameters, then any `{{input:...}}` that is not declared
+pub fn inputs(template: &str, parameters: &[CommandParameter]) -> Vec<CommandParameter> {
+    let mut inputs = parameters.to_vec();
+    for segment in parse(template, parameters) {
+        if let Segment::Placeholder(Placeholder::Input(label)) = segment {
+            if !inputs.iter().any(|p| p.name == label) {
+                inputs.push(CommandParameter {
+                    name: label.to_string(),
+                    kind: ParameterType::String,
+                    default: None,
+                    choices: Vec::new(),
+                    required: true,
+                });
+            }
+        }
+    }
+    inputs
+}
+
+/// Fill in the placeholders of a command. Substituted values are quoted for
+/// the shell, so placeholders belong outside of quotes. Every missing or
+/// invalid value is reported, not just the first.
+pub fn resolve(
+    template: &str,
+    parameters: &[CommandParameter],
+    context: &ParameterContext,
+    values: &HashMap<String, String>,
+) -> Result<String, Vec<String>> {
+    let segments = parse(template, parameters);
+    let mut issues = Vec::new();
+    let mut resolved = String::new();
+    let mut branch: Option<Option<String>> = None;
+
+    for segment in segments {
+        let placeholder = match segment {
+            Segment::Text(text) => {
+                resolved.push_str(text);
+                continue;
+            }
+            Segment::Placeholder(placeholder) => placeholder,
+        };
+
+        let value = match placeholder {
+            Placeholder::Branch if parameters.iter().any(|p| p.name == "branch") => {
+                parameter_value("branch", parameters, values)
+            }
+            Placeholder::Branch => branch
+                .get_or_insert_with(|| (context.branch)())
+                .clone()
+                .ok_or_else(|| "{{branch}} needs the project to be a git repository".to_string()),
+            Placeholder::Env(variable) => context
+                .environment
+                .and_then(|e| e.variables.get(variable).cloned())
+                .or_else(|| std::env::var(variable).ok())
+                .ok_or_else(|| format!("Environment variable {} is not set", variable)),
+            Placeholder::Project(field) => match field {
+                "path" => Ok(context.project.path.clone()),
+                "name" => Ok(context.project.name.clone()),
+                "id" => Ok(context.project.id.clone()),
+                _ => Err(format!("Unknown project field: {{{{project.{}}}}}", field)),
+            },
+            Placeholder::Input(label) => parameter_value(label, parameters, values),
+            Placeholder::Parameter(name) => parameter_value(name, parameters, values),
+        };
+
+        match value {
+            // An optional value that was left out disappears entirely
+            Ok(value) if value.is_empty() => {}
+            Ok(value) => resolved.push_str(&shell::quote_arg(&value)),
+            Err(issue) => {
+                if !issues.contains(&issue) {
+                    issues.push(issue);
+                }
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(resolved)
+    } else {
+        Err(issues)
+    }
+}
+
+/// The given or default value of a parameter, checked against its type.
+/// Undeclared inputs are required strings.
+fn parameter_value(
+    name: &str,
+    parameters: &[CommandParameter],
+    values: &HashMap<String, String>,
+) -> Result<String, String> {
+    let parameter = parameters.iter().find(|p| p.name == name);
+    let value = values
+        .get(name)
+        .filter(|v| !v.is_empty())
+        .cloned()
+        .or_else(|| parameter.and_then(|p| p.default.clone()));
+
+    let Some(value) = value else {
+        return match parameter {
+            Some(p) if !p.required => Ok(String::new()),
+            _ => Err(format!("A value for {} is required", name)),
+        };
+    };
+
+    match parameter.map(|p| (p.kind, &p.choices)) {
+        Some((ParameterType::Number, _)) if value.parse::<f64>().is_err() => {
+            Err(format!("{} must be a number, got {}", name, value))
+        }
+        Some((ParameterType::Boolean, _)) if value != "true" && value != "false" => {
+            Err(format!("{} must be true or false, got {}", name, value))
+        }
+        Some((ParameterType::Choice, choices)) if !choices.contains(&value) => Err(format!(
+            "{} must be one of {}, got {}",
+            name,
+            choices.join(", "),
+            value
+        )),
+        _ => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{self, environment};
+
+    fn parameter(
+        name: &str,
+        kind: ParameterType,
+        default: Option<&str>,
+        choices: &[&str],
+    ) -> CommandParameter {
+        CommandParameter {
+            name: name.to_string(),
+            kind,
+            default: default.map(|d| d.to_string()),
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+            required: true,
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let project = fixtures::project("/work/my app");
+        let environment = environment("dev", &[("PORT", "4000")]);
+        let branch = || Some("feature/x".to_string());
+        let context = ParameterContext {
+            project: &project,
+            environment: Some(&environment),
+            branch: &branch,
+        };
+        let parameters = vec![
+            parameter(
+                "target",
+                ParameterType::Choice,
+                Some("debug"),
+                &["debug", "release"],
+            ),
+            parameter("jobs", ParameterType::Number, None, &[]),
+            CommandParameter {
+                required: false,
+                ..parameter("flags", ParameterType::String, None, &[])
+            },
+        ];
+        let values = HashMap::from([
+            ("jobs".to_string(), "4".to_string()),
+            (
+                "Migration name".to_string(),
+                "add users; rm -rf /".to_string(),
+            ),
+        ]);
+
+        let resolved = resolve(
+            "cd {{project.path}} && git checkout {{branch}} && PORT={{ env.PORT }} make {{target}} -j{{jobs}} {{flags}}&& migrate {{input:Migration name}}",
+            &parameters,
+            &context,
+            &values,
+        );
+        assert_eq!(
+            resolved.unwrap(),
+            "cd '/work/my app' && git checkout feature/x && PORT=4000 make debug -j4 && migrate 'add users; rm -rf /'"
+        );
+
+        let values = HashMap::from([
+            ("target".to_string(), "fast".to_string()),
+            ("jobs".to_string(), "many".to_string()),
+        ]);
+        let no_branch = || None;
+        let context = ParameterContext {
+            branch: &no_branch,
+            ..context
+        };
+        let issues = resolve(
+            "{{target}} {{jobs}} {{input:Name}} {{branch}} {{env.CMDR_TEST_UNSET}}",
+            &parameters,
+            &context,
+            &values,
+        )
+        .unwrap_err();
+        assert_eq!(
+            issues,
+            vec![
+                "target must be one of debug, release, got fast",
+                "jobs must be a number, got many",
+                "A value for Name is required",
+                "{{branch}} needs the project to be a git repository",
+                "Environment variable CMDR_TEST_UNSET is not set",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_keeps_other_braces() {
+        let project = fixtures::project("/work/app");
+        let branch = || None;
+        let context = ParameterContext {
+            project: &project,
+            environment: None,
+            branch: &branch,
+        };
+        let resolve = |template: &str| resolve(template, &[], &context, &HashMap::new());
+
+        assert_eq!(
+            resolve("docker ps --format '{{.Names}}' --filter name={{project.name}}").unwrap(),
+            "docker ps --format '{{.Names}}' --filter name=App"
+        );
+        assert_eq!(
+            resolve("echo {{unknown}} {{oops").unwrap(),
+            "echo {{unknown}} {{oops"
+        );
+    }
+
+    #[test]
+    fn test_inputs() {
+        let parameters = vec![parameter(
+            "target",
+            ParameterType::Choice,
+            None,
+            &["a", "b"],
+        )];
+        let inputs = inputs(
+            "make {{target}} {{input:Name}} {{input:target}} {{branch}}",
+            &parameters,
+        );
+
+        let names: Vec<&str> = inputs.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["target", "Name"]);
+        assert_eq!(inputs[1].kind, ParameterType::String);
+    }
+}
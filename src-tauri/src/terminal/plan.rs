@@ -0,0 +1,693 @@
+use super::{resolve_directory, shell};
+use crate::config::{Environment, LayoutNode, Pane, Project, Workspace, WorkspaceLayout};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub use crate::config::SplitDirection;
+
+/// Everything a backend needs to open a workspace, worked out up front so
+/// it can be previewed and tested without touching a terminal
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchPlan {
+    pub project_id: String,
+    pub workspace_id: String,
+    pub workspace_name: String,
+    /// Session name for multiplexers such as tmux
+    pub session_name: String,
+    /// Name of the project environment applied to every pane
+    pub environment: Option<String>,
+    pub windows: Vec<WindowPlan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowPlan {
+    pub tabs: Vec<TabPlan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TabPlan {
+    pub title: Option<String>,
+    pub layout: SplitNode,
+}
+
+/// Layout of a tab as a tree of splits with panes at the leaves
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SplitNode {
+    Pane(PanePlan),
+    Split {
+        direction: SplitDirection,
+        children: Vec<SplitChild>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitChild {
+    /// Percentage of the parent taken by this child
+    pub size: u32,
+    pub node: SplitNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PanePlan {
+    /// Grid position the pane came from, if any
+    pub position: Option<(u32, u32)>,
+    pub cwd: String,
+    pub env: BTreeMap<String, String>,
+    pub commands: Vec<String>,
+}
+
+/// One split needed to build a layout from a single pane: split the pane in
+/// slot `target` in `direction` to create slot `pane`, which takes `percent`
+/// of the target's space. Slot 0 is the pane a new tab starts with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitStep {
+    pub target: usize,
+    pub pane: usize,
+    pub direction: SplitDirection,
+    pub percent: u32,
+}
+
+impl LaunchPlan {
+    pub fn build(
+        project: &Project,
+        workspace: &Workspace,
+        environment: Option<&Environment>,
+    ) -> Self {
+        let first = TabPlan {
+            title: workspace.title.clone(),
+            layout: tab_layout(project, &workspace.layout, &workspace.panes, environment),
+        };
+
+        // Tabs are grouped by window number, keeping their order within a
+        // window; gaps in the numbering are closed up
+        let mut windows: BTreeMap<u32, Vec<TabPlan>> = BTreeMap::new();
+        windows.entry(0).or_default().push(first);
+        for tab in &workspace.tabs {
+            windows.entry(tab.window).or_default().push(TabPlan {
+                title: tab.title.clone(),
+                layout: tab_layout(project, &tab.layout, &tab.panes, environment),
+            });
+        }
+
+        LaunchPlan {
+            project_id: project.id.clone(),
+            workspace_id: workspace.id.clone(),
+            workspace_name: workspace.name.clone(),
+            session_name: session_name(project, workspace),
+            environment: environment.map(|e| e.name.clone()),
+            windows: windows
+                .into_values()
+                .map(|tabs| WindowPlan { tabs })
+                .collect(),
+        }
+    }
+
+    /// Every pane in the plan, window by window and tab by tab
+    pub fn panes(&self) -> Vec<&PanePlan> {
+        self.windows
+            .iter()
+            .flat_map(|w| w.tabs.iter())
+            .flat_map(|t| t.layout.split_steps().1)
+            .collect()
+    }
+}
+
+impl PanePlan {
+    /// A single pane running `command` in the project directory
+    pub fn for_command(
+        project: &Project,
+        command: &str,
+        environment: Option<&Environment>,
+    ) -> Self {
+        PanePlan {
+            position: None,
+            cwd: project.path.clone(),
+            env: shell::pane_environment(environment, None),
+            commands: vec![command.to_string()],
+        }
+    }
+
+    /// Lines to type into the pane's shell: the exports, the `cd`, then the
+    /// commands
+    pub fn shell_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = shell::export_line(&self.env).into_iter().collect();
+        lines.push(shell::cd_line(&self.cwd));
+        lines.extend(self.commands.iter().cloned());
+        lines
+    }
+
+    /// Script for terminals that start a pane with a program instead of
+    /// typing into its shell: the commands, then the user's shell
+    pub fn command_script(&self) -> String {
+        let mut lines = self.commands.clone();
+        lines.push("exec \"${SHELL:-sh}\"".to_string());
+        lines.join("; ")
+    }
+
+    /// Whether the pane has to be started with a script rather than a
+    /// plain shell
+    pub fn needs_script(&self) -> bool {
+        !self.env.is_empty() || !self.commands.is_empty()
+    }
+
+    /// `command_script` for terminals that cannot set variables on a
+    /// pane, with the variables exported first
+    pub fn startup_script(&self) -> String {
+        match shell::export_line(&self.env) {
+            Some(exports) => format!("{}; {}", exports, self.command_script()),
+            None => self.command_script(),
+        }
+    }
+
+    fn from_pane(
+        project: &Project,
+        pane: Option<&Pane>,
+        environment: Option<&Environment>,
+    ) -> Self {
+        PanePlan {
+            position: pane.map(|p| p.position),
+            cwd: pane
+                .map(|p| resolve_directory(&project.path, &p.directory))
+                .unwrap_or_else(|| project.path.clone()),
+            env: shell::pane_environment(environment, pane),
+            commands: pane.and_then(|p| p.command.clone()).into_iter().collect(),
+        }
+    }
+}
+
+/// A pane of a layout with the slot it ends up in and its share of the
+/// tab's width and height
+struct PaneSlot<'a> {
+    slot: usize,
+    pane: &'a PanePlan,
+    width: f64,
+    height: f64,
+}
+
+impl SplitNode {
+    /// The splits that build this layout, in order, and the panes indexed
+    /// by the slot they end up in.
+    ///
+    /// All children of a split are created before any of them is split
+    /// further, so each split acts on a pane that still covers the whole
+    /// region of the remaining children.
+    pub fn split_steps(&self) -> (Vec<SplitStep>, Vec<&PanePlan>) {
+        let (steps, slots) = self.walk();
+        (steps, slots.into_iter().map(|s| s.pane).collect())
+    }
+
+    /// Percentage of the tab's width and height each slot should cover, for
+    /// terminals that size panes after splitting
+    pub fn pane_extents(&self) -> Vec<(u32, u32)> {
+        self.walk()
+            .1
+            .into_iter()
+            .map(|s| (s.width.round() as u32, s.height.round() as u32))
+            .collect()
+    }
+
+    fn walk(&self) -> (Vec<SplitStep>, Vec<PaneSlot<'_>>) {
+        let mut steps = Vec::new();
+        let mut slots = Vec::new();
+        let mut next_slot = 1;
+        collect_steps(
+            self,
+            0,
+            (100.0, 100.0),
+            &mut next_slot,
+            &mut steps,
+            &mut slots,
+        );
+
+        slots.sort_by_key(|s| s.slot);
+        (steps, slots)
+    }
+}
+
+fn collect_steps<'a>(
+    node: &'a SplitNode,
+    slot: usize,
+    extent: (f64, f64),
+    next_slot: &mut usize,
+    steps: &mut Vec<SplitStep>,
+    panes: &mut Vec<PaneSlot<'a>>,
+) {
+    match node {
+        SplitNode::Pane(pane) => panes.push(PaneSlot {
+            slot,
+            pane,
+            width: extent.0,
+            height: extent.1,
+        }),
+        SplitNode::Split {
+            direction,
+            children,
+        } => {
+            let mut slots = vec![slot];
+            for i in 1..children.len() {
+                let remaining: u32 = children[i..].iter().map(|c| c.size).sum();
+                let current: u32 = children[i - 1..].iter().map(|c| c.size).sum();
+                let new_slot = *next_slot;
+                *next_slot += 1;
+
+                steps.push(SplitStep {
+                    target: slots[i - 1],
+                    pane: new_slot,
+                    direction: *direction,
+                    percent: (remaining * 100).checked_div(current).unwrap_or(50),
+                });
+                slots.push(new_slot);
+            }
+
+            for (child, child_slot) in children.iter().zip(slots) {
+                let share = child.size as f64 / 100.0;
+                let child_extent = match direction {
+                    SplitDirection::Horizontal => (extent.0 * share, extent.1),
+                    SplitDirection::Vertical => (extent.0, extent.1 * share),
+                };
+                collect_steps(
+                    &child.node,
+                    child_slot,
+                    child_extent,
+                    next_slot,
+                    steps,
+                    panes,
+                );
+            }
+        }
+    }
+}
+
+/// The split tree for one tab's layout and panes
+fn tab_layout(
+    project: &Project,
+    layout: &WorkspaceLayout,
+    panes: &[Pane],
+    environment: Option<&Environment>,
+) -> SplitNode {
+    match &layout.split {
+        Some(root) => tree_layout(project, panes, environment, root),
+        None => grid_layout(project, layout, panes, environment),
+    }
+}
+
+/// Turn a split-tree layout from the config into the plan's tree. Leaves
+/// without a pane become a shell in the project directory.
+fn tree_layout(
+    project: &Project,
+    panes: &[Pane],
+    environment: Option<&Environment>,
+    node: &LayoutNode,
+) -> SplitNode {
+    match node {
+        LayoutNode::Pane { pane, .. } => {
+            let pane = pane.and_then(|index| panes.get(index));
+            let mut plan = PanePlan::from_pane(project, pane, environment);
+            // Grid positions mean nothing in a split tree
+            plan.position = None;
+            SplitNode::Pane(plan)
+        }
+        LayoutNode::Split {
+            direction,
+            children,
+            ..
+        } => {
+            if children.len() <= 1 {
+                return match children.first() {
+                    Some(child) => tree_layout(project, panes, environment, child),
+                    None => SplitNode::Pane(PanePlan::from_pane(project, None, environment)),
+                };
+            }
+
+            let sizes: Vec<Option<u32>> = children.iter().map(|c| c.size()).collect();
+            let children = children
+                .iter()
+                .zip(split_sizes(&sizes))
+                .map(|(child, size)| SplitChild {
+                    size,
+                    node: tree_layout(project, panes, environment, child),
+                })
+                .collect();
+
+            SplitNode::Split {
+                direction: *direction,
+                children,
+            }
+        }
+    }
+}
+
+/// Resolve a split's child sizes into percentages that add up to 100.
+/// Children without a size share what the others leave, and sizes that do
+/// not add up to 100 are scaled as weights.
+fn split_sizes(sizes: &[Option<u32>]) -> Vec<u32> {
+    let explicit: u32 = sizes.iter().flatten().sum();
+    let missing = sizes.iter().filter(|s| s.is_none()).count() as u32;
+    let share = (100u32.saturating_sub(explicit) / missing.max(1)).max(1);
+
+    let weights: Vec<u32> = sizes.iter().map(|s| s.unwrap_or(share).max(1)).collect();
+    let total: u32 = weights.iter().sum();
+
+    let mut percents: Vec<u32> = weights.iter().map(|w| w * 100 / total).collect();
+    // Give the rounding remainder to the first child so sizes add up to 100
+    let assigned: u32 = percents.iter().sum();
+    if let Some(first) = percents.first_mut() {
+        *first += 100 - assigned;
+    }
+    percents
+}
+
+/// Turn the rows/columns grid into a split tree: a vertical stack of rows,
+/// each a horizontal split of equally sized columns
+fn grid_layout(
+    project: &Project,
+    layout: &WorkspaceLayout,
+    panes: &[Pane],
+    environment: Option<&Environment>,
+) -> SplitNode {
+    let pane_at = |row: usize, col: usize| {
+        let pane = panes
+            .iter()
+            .find(|p| p.position.0 as usize == row && p.position.1 as usize == col);
+        let mut plan = PanePlan::from_pane(project, pane, environment);
+        plan.position = Some((row as u32, col as u32));
+        SplitNode::Pane(plan)
+    };
+
+    let rows: Vec<SplitNode> = layout
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(row, &cols)| {
+            let cols: Vec<SplitNode> = (0..cols.max(1) as usize)
+                .map(|col| pane_at(row, col))
+                .collect();
+            equal_split(SplitDirection::Horizontal, cols)
+        })
+        .collect();
+
+    if rows.is_empty() {
+        return pane_at(0, 0);
+    }
+    equal_split(SplitDirection::Vertical, rows)
+}
+
+/// Split evenly between `nodes`, or just the node when there is only one
+fn equal_split(direction: SplitDirection, mut nodes: Vec<SplitNode>) -> SplitNode {
+    if nodes.len() == 1 {
+        return nodes.remove(0);
+    }
+
+    let count = nodes.len() as u32;
+    let children = nodes
+        .into_iter()
+        .enumerate()
+        .map(|(i, node)| SplitChild {
+            // Give the rounding remainder to the first child so sizes add up to 100
+            size: 100 / count + if i == 0 { 100 % count } else { 0 },
+            node,
+        })
+        .collect();
+
+    SplitNode::Split {
+        direction,
+        children,
+    }
+}
+
+/// Name of the multiplexer session for a workspace, e.g.
+/// `cmdr-my-app-dev-3f2a9c1d`. The start of the workspace id keeps
+/// workspaces with the same names apart, and stands in for a name with
+/// nothing to slug. tmux does not allow `.` or `:` in session names.
+pub fn session_name(project: &Project, workspace: &Workspace) -> String {
+    let id = short_id(&workspace.id);
+    let project_name = match slugify(&project.name) {
+        slug if slug.is_empty() => short_id(&project.id),
+        slug => slug,
+    };
+    match slugify(&workspace.name) {
+        slug if slug.is_empty() => format!("cmdr-{}-{}", project_name, id),
+        slug => format!("cmdr-{}-{}-{}", project_name, slug, id),
+    }
+}
+
+/// The first 8 characters of an id, as they may appear in a session name
+fn short_id(id: &str) -> String {
+    slugify(id).chars().take(8).collect()
+}
+
+fn slugify(s: &str) -> String {
+    let mut slug = String::new();
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{self, environment, layout};
+    use crate::config::WorkspaceTab;
+    use std::collections::HashMap;
+
+    fn project() -> Project {
+        Project {
+            name: "My App".to_string(),
+            ..fixtures::project("/work/app")
+        }
+    }
+
+    fn workspace(columns: Vec<u32>, panes: Vec<Pane>) -> Workspace {
+        Workspace {
+            name: "Dev: Servers".to_string(),
+            ..fixtures::workspace(columns, panes)
+        }
+    }
+
+    #[test]
+    fn test_session_name() {
+        assert_eq!(
+            session_name(&project(), &workspace(vec![1], Vec::new())),
+            "cmdr-my-app-dev-servers-w1"
+        );
+
+        // Names are told apart by the workspace id, which also stands in
+        // for a name that has nothing to slug
+        let unnamed = Project {
+            id: "0f3c2a9d-41be".to_string(),
+            name: "アプリ".to_string(),
+            ..project()
+        };
+        let other = Workspace {
+            id: "9b1e47c0-77aa".to_string(),
+            name: "::".to_string(),
+            ..workspace(vec![1], Vec::new())
+        };
+        assert_eq!(session_name(&project(), &other), "cmdr-my-app-9b1e47c0");
+        assert_eq!(
+            session_name(&unnamed, &workspace(vec![1], Vec::new())),
+            "cmdr-0f3c2a9d-dev-servers-w1"
+        );
+    }
+
+    #[test]
+    fn test_build_grid_plan() {
+        let pane = Pane {
+            environment_variables: Some(HashMap::from([("PORT".to_string(), "4000".to_string())])),
+            ..fixtures::pane((0, 1), "api", Some("npm run dev"))
+        };
+        let environment = environment("staging", &[("STAGE", "staging")]);
+
+        let plan = LaunchPlan::build(
+            &project(),
+            &workspace(vec![2, 1], vec![pane]),
+            Some(&environment),
+        );
+
+        assert_eq!(plan.session_name, "cmdr-my-app-dev-servers-w1");
+        assert_eq!(plan.environment.as_deref(), Some("staging"));
+        assert_eq!(plan.windows.len(), 1);
+        assert_eq!(plan.windows[0].tabs.len(), 1);
+
+        let panes = plan.panes();
+        assert_eq!(panes.len(), 3);
+        // Slot order: first pane, the second row, then the second column
+        assert_eq!(panes[0].position, Some((0, 0)));
+        assert_eq!(panes[0].cwd, "/work/app");
+        assert!(panes[0].commands.is_empty());
+        assert_eq!(panes[1].position, Some((1, 0)));
+        assert_eq!(panes[2].position, Some((0, 1)));
+        assert_eq!(panes[2].cwd, "/work/app/api");
+        assert_eq!(panes[2].commands, vec!["npm run dev".to_string()]);
+        assert_eq!(panes[2].env.get("PORT").map(String::as_str), Some("4000"));
+        assert_eq!(
+            panes[2].env.get("STAGE").map(String::as_str),
+            Some("staging")
+        );
+    }
+
+    #[test]
+    fn test_split_steps() {
+        let plan = LaunchPlan::build(&project(), &workspace(vec![3, 1], Vec::new()), None);
+        let (steps, panes) = plan.windows[0].tabs[0].layout.split_steps();
+
+        assert_eq!(
+            steps,
+            vec![
+                SplitStep {
+                    target: 0,
+                    pane: 1,
+                    direction: SplitDirection::Vertical,
+                    percent: 50
+                },
+                SplitStep {
+                    target: 0,
+                    pane: 2,
+                    direction: SplitDirection::Horizontal,
+                    percent: 66
+                },
+                SplitStep {
+                    target: 2,
+                    pane: 3,
+                    direction: SplitDirection::Horizontal,
+                    percent: 50
+                },
+            ]
+        );
+        let positions: Vec<_> = panes.iter().map(|p| p.position.unwrap()).collect();
+        assert_eq!(positions, vec![(0, 0), (1, 0), (0, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn test_split_sizes() {
+        assert_eq!(split_sizes(&[Some(70), None]), vec![70, 30]);
+        assert_eq!(split_sizes(&[None, None, None]), vec![34, 33, 33]);
+        assert_eq!(split_sizes(&[Some(60), Some(20)]), vec![75, 25]);
+    }
+
+    #[test]
+    fn test_build_tree_plan() {
+        let pane = |command: &str| fixtures::pane((0, 0), ".", Some(command));
+        let leaf = |index: usize| LayoutNode::Pane {
+            pane: Some(index),
+            size: None,
+        };
+        let mut ws = workspace(
+            Vec::new(),
+            vec![pane("vim"), pane("a"), pane("b"), pane("c")],
+        );
+        // A 70% editor on the left, three panes stacked on the right
+        ws.layout.split = Some(LayoutNode::Split {
+            direction: SplitDirection::Horizontal,
+            size: None,
+            children: vec![
+                LayoutNode::Pane {
+                    pane: Some(0),
+                    size: Some(70),
+                },
+                LayoutNode::Split {
+                    direction: SplitDirection::Vertical,
+                    size: None,
+                    children: vec![leaf(1), leaf(2), leaf(3)],
+                },
+            ],
+        });
+
+        let plan = LaunchPlan::build(&project(), &ws, None);
+        let layout = &plan.windows[0].tabs[0].layout;
+        let (steps, panes) = layout.split_steps();
+
+        assert_eq!(
+            steps,
+            vec![
+                SplitStep {
+                    target: 0,
+                    pane: 1,
+                    direction: SplitDirection::Horizontal,
+                    percent: 30
+                },
+                SplitStep {
+                    target: 1,
+                    pane: 2,
+                    direction: SplitDirection::Vertical,
+                    percent: 66
+                },
+                SplitStep {
+                    target: 2,
+                    pane: 3,
+                    direction: SplitDirection::Vertical,
+                    percent: 50
+                },
+            ]
+        );
+        let commands: Vec<_> = panes.iter().map(|p| p.commands[0].as_str()).collect();
+        assert_eq!(commands, vec!["vim", "a", "b", "c"]);
+        assert!(panes.iter().all(|p| p.position.is_none()));
+        assert_eq!(
+            layout.pane_extents(),
+            vec![(70, 100), (30, 34), (30, 33), (30, 33)]
+        );
+    }
+
+    #[test]
+    fn test_build_tabs_and_windows() {
+        let tab = |title: &str, window: u32, columns: Vec<u32>| WorkspaceTab {
+            title: Some(title.to_string()),
+            window,
+            layout: layout(columns),
+            panes: Vec::new(),
+        };
+        let mut ws = workspace(vec![2], Vec::new());
+        ws.title = Some("servers".to_string());
+        ws.tabs = vec![tab("db", 3, vec![1]), tab("logs", 0, vec![1, 1])];
+
+        let plan = LaunchPlan::build(&project(), &ws, None);
+
+        let titles: Vec<Vec<_>> = plan
+            .windows
+            .iter()
+            .map(|w| w.tabs.iter().map(|t| t.title.as_deref().unwrap()).collect())
+            .collect();
+        assert_eq!(titles, vec![vec!["servers", "logs"], vec!["db"]]);
+        assert_eq!(plan.panes().len(), 5);
+    }
+
+    #[test]
+    fn test_shell_lines() {
+        let pane = PanePlan {
+            position: None,
+            cwd: "/work/my app".to_string(),
+            env: BTreeMap::from([("PORT".to_string(), "3000".to_string())]),
+            commands: vec!["npm start".to_string()],
+        };
+        assert_eq!(
+            pane.shell_lines(),
+            vec!["export PORT='3000'", "cd '/work/my app'", "npm start"]
+        );
+        assert_eq!(
+            pane.startup_script(),
+            "export PORT='3000'; npm start; exec \"${SHELL:-sh}\""
+        );
+    }
+
+    #[test]
+    fn test_plan_serializes_for_preview() {
+        let plan = LaunchPlan::build(&project(), &workspace(vec![1], Vec::new()), None);
+        let json = serde_json::to_value(&plan).unwrap();
+
+        assert_eq!(json["sessionName"], "cmdr-my-app-dev-servers-w1");
+        assert_eq!(json["windows"][0]["tabs"][0]["layout"]["type"], "pane");
+        assert_eq!(json["windows"][0]["tabs"][0]["layout"]["cwd"], "/work/app");
+    }
+}
@@ -0,0 +1,314 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// File in the config directory that remembers the processes cmdr started
+/// for each workspace session
+const PROCESSES_FILE: &str = "processes.json";
+
+/// How long processes get to exit after the graceful signal before they
+/// are killed
+pub const GRACE_PERIOD: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Held from loading the registry until it is saved, since workspaces can
+/// be launched and stopped at the same time
+static PROCESSES_LOCK: Mutex<()> = Mutex::new(());
+
+/// A process cmdr started, with its start time so that a recycled pid is
+/// never mistaken for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedProcess {
+    pub pid: u32,
+    #[serde(default, alias = "started_at")]
+    pub started_at: Option<String>,
+}
+
+/// Processes started for each workspace session, keyed by session name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcessRegistry {
+    #[serde(default)]
+    sessions: BTreeMap<String, Vec<TrackedProcess>>,
+}
+
+impl ProcessRegistry {
+    fn lock() -> MutexGuard<'static, ()> {
+        PROCESSES_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn load() -> Self {
+        let path = match crate::config::get_config_dir() {
+            Ok(dir) => dir.join(PROCESSES_FILE),
+            Err(_) => return Self::default(),
+        };
+        std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = crate::config::get_config_dir()?.join(PROCESSES_FILE);
+        log::debug!("[Process] Saving tracked processes to: {:?}", path);
+        // Renamed over the old file so that it is never read half written
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+}
+
+/// Remember a process started for a workspace session, so that
+/// `stop_workspace` can end it later
+pub fn track(session: &str, pid: u32) {
+    let process = TrackedProcess {
+        pid,
+        started_at: start_time(pid),
+    };
+    let _guard = ProcessRegistry::lock();
+    let mut registry = ProcessRegistry::load();
+    registry
+        .sessions
+        .entry(session.to_string())
+        .or_default()
+        .push(process);
+
+    if let Err(e) = registry.save() {
+        log::warn!(
+            "[Process] Could not record process {} for {}: {}",
+            pid,
+            session,
+            e
+        );
+    }
+}
+
+/// Forget the processes of a session and return those that are still the
+/// processes cmdr started
+pub fn take_tracked(session: &str) -> Result<Vec<u32>> {
+    let tracked = {
+        let _guard = ProcessRegistry::lock();
+        let mut registry = ProcessRegistry::load();
+        let tracked = registry.sessions.remove(session).unwrap_or_default();
+        registry.save()?;
+        tracked
+    };
+
+    Ok(tracked
+        .into_iter()
+        .filter(|p| p.started_at.is_some() && start_time(p.pid) == p.started_at)
+        .map(|p| p.pid)
+        .collect())
+}
+
+/// End the process trees under `roots`: every process gets the graceful
+/// signal, and whatever is left after `grace` is killed. With
+/// `include_roots` unset only the descendants are signalled, for roots such
+/// as interactive shells that ignore the graceful signal.
+pub fn terminate_trees(roots: &[u32], include_roots: bool, grace: Duration) {
+    let table = process_table();
+    let mut pids = descendants(&table, roots);
+    if include_roots {
+        pids.extend(roots.iter().copied());
+    }
+    if pids.is_empty() {
+        return;
+    }
+
+    log::info!("[Process] Stopping processes {:?}", pids);
+    signal(&pids, Signal::Terminate);
+
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline {
+        pids.retain(|pid| is_alive(*pid));
+        if pids.is_empty() {
+            return;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    pids.retain(|pid| is_alive(*pid));
+    if !pids.is_empty() {
+        log::warn!("[Process] Killing processes that did not exit: {:?}", pids);
+        signal(&pids, Signal::Kill);
+    }
+}
+
+/// Every process below `roots`, deepest first, so that children are
+/// signalled before the parents that might restart them
+pub fn descendants(table: &[(u32, u32)], roots: &[u32]) -> Vec<u32> {
+    let mut found = Vec::new();
+    let mut frontier: Vec<u32> = roots.to_vec();
+
+    while !frontier.is_empty() {
+        let children: Vec<u32> = table
+            .iter()
+            .filter(|(pid, ppid)| {
+                frontier.contains(ppid) && !found.contains(pid) && !roots.contains(pid)
+            })
+            .map(|(pid, _)| *pid)
+            .collect();
+        found.extend(&children);
+        frontier = children;
+    }
+
+    found.reverse();
+    found
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Signal {
+    Terminate,
+    Kill,
+}
+
+/// `(pid, parent pid)` of every process on the system
+#[cfg(unix)]
+pub fn process_table() -> Vec<(u32, u32)> {
+    let output = match Command::new("ps").args(["-A", "-o", "pid=,ppid="]).output() {
+        Ok(output) => output,
+        Err(e) => {
+            log::error!("[Process] Failed to execute ps: {}", e);
+            return Vec::new();
+        }
+    };
+    parse_process_table(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Windows ends whole trees with `taskkill /T`, so no table is needed
+#[cfg(not(unix))]
+pub fn process_table() -> Vec<(u32, u32)> {
+    Vec::new()
+}
+
+fn parse_process_table(output: &str) -> Vec<(u32, u32)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            Some((pid, ppid))
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn signal(pids: &[u32], signal: Signal) {
+    let name = match signal {
+        Signal::Terminate => "-TERM",
+        Signal::Kill => "-KILL",
+    };
+    let pids: Vec<String> = pids.iter().map(|pid| pid.to_string()).collect();
+    // Processes that exit in the meantime make kill fail, which is fine
+    let _ = Command::new("kill").arg(name).args(&pids).output();
+}
+
+#[cfg(not(unix))]
+fn signal(pids: &[u32], signal: Signal) {
+    for pid in pids {
+        let mut cmd = Command::new("taskkill");
+        cmd.args(["/T", "/PID", &pid.to_string()]);
+        if let Signal::Kill = signal {
+            cmd.arg("/F");
+        }
+        let _ = cmd.output();
+    }
+}
+
+/// Whether a process is running. Zombies count as gone: they have exited
+/// and only wait for their parent to reap them.
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    Command::new("ps")
+        .args(["-o", "stat=", "-p", &pid.to_string()])
+        .output()
+        .map(|output| {
+            let stat = String::from_utf8_lossy(&output.stdout);
+            let stat = stat.trim();
+            output.status.success() && !stat.is_empty() && !stat.starts_with('Z')
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(false)
+}
+
+/// The full command line of a process, as reported by `ps`
+#[cfg(unix)]
+pub fn command_line(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "args=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let args = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !args.is_empty()).then_some(args)
+}
+
+#[cfg(not(unix))]
+pub fn command_line(_pid: u32) -> Option<String> {
+    None
+}
+
+/// When a process started, as reported by `ps`
+#[cfg(unix)]
+fn start_time(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "lstart=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let started = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !started.is_empty()).then_some(started)
+}
+
+#[cfg(not(unix))]
+fn start_time(pid: u32) -> Option<String> {
+    is_alive(pid).then(|| pid.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descendants() {
+        let table = parse_process_table(
+            "    1     0\n  100     1\n  101   100\n  102   101\n  103   100\n  200     1\n",
+        );
+        assert_eq!(table.len(), 6);
+
+        // Deepest first, unrelated processes and the roots left out
+        assert_eq!(descendants(&table, &[100]), vec![102, 103, 101]);
+        assert!(descendants(&table, &[200]).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_trees() {
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 30 & wait"])
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        thread::sleep(Duration::from_millis(200));
+        let sleeper = descendants(&process_table(), &[pid]);
+        assert_eq!(sleeper.len(), 1);
+
+        terminate_trees(&[pid], true, Duration::from_secs(2));
+        child.wait().unwrap();
+        assert!(!is_alive(sleeper[0]));
+        assert!(start_time(pid).is_none());
+    }
+}
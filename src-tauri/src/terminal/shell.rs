@@ -0,0 +1,152 @@
+use crate::config::{Environment, Pane};
+use std::collections::BTreeMap;
+
+/// Quote a string for POSIX shells
+pub fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Quote a command argument only when it needs it, keeping generated
+/// command lines readable
+pub fn quote_arg(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-=./:,@%+".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        s.to_string()
+    } else {
+        quote(s)
+    }
+}
+
+/// Whether `name` can be used as a shell variable name
+pub fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+/// Variables for a pane: the selected project environment, overridden by
+/// the pane's own variables. Names that are not valid shell identifiers are
+/// dropped.
+pub fn pane_environment(
+    environment: Option<&Environment>,
+    pane: Option<&Pane>,
+) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+
+    if let Some(env) = environment {
+        vars.extend(env.variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    if let Some(pane_vars) = pane.and_then(|p| p.environment_variables.as_ref()) {
+        vars.extend(pane_vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+
+    vars.into_iter()
+        .filter(|(name, _)| {
+            let valid = is_valid_env_name(name);
+            if !valid {
+                log::warn!(
+                    "[Terminal] Skipping invalid environment variable name: {:?}",
+                    name
+                );
+            }
+            valid
+        })
+        .collect()
+}
+
+/// A single `export` statement for `vars`, or `None` when there are none
+pub fn export_line(vars: &BTreeMap<String, String>) -> Option<String> {
+    if vars.is_empty() {
+        return None;
+    }
+
+    let assignments: Vec<String> = vars
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, quote(value)))
+        .collect();
+    Some(format!("export {}", assignments.join(" ")))
+}
+
+/// Shell line that changes into `dir`
+pub fn cd_line(dir: &str) -> String {
+    format!("cd {}", quote(dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigLayer;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("/tmp/my app"), "'/tmp/my app'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote_arg("--cwd=/tmp/app"), "--cwd=/tmp/app");
+        assert_eq!(quote_arg("--cwd=/tmp/my app"), "'--cwd=/tmp/my app'");
+        assert_eq!(quote_arg(""), "''");
+    }
+
+    #[test]
+    fn test_is_valid_env_name() {
+        assert!(is_valid_env_name("PORT"));
+        assert!(is_valid_env_name("_private_1"));
+        assert!(!is_valid_env_name("1PORT"));
+        assert!(!is_valid_env_name("MY-VAR"));
+        assert!(!is_valid_env_name("$(rm -rf ~)"));
+        assert!(!is_valid_env_name(""));
+    }
+
+    #[test]
+    fn test_pane_environment_precedence() {
+        let environment = Environment {
+            name: "staging".to_string(),
+            variables: HashMap::from([
+                (
+                    "API_URL".to_string(),
+                    "https://staging.example.com".to_string(),
+                ),
+                ("PORT".to_string(), "3000".to_string()),
+            ]),
+            layer: ConfigLayer::User,
+            shared_variables: Vec::new(),
+        };
+        let pane = Pane {
+            position: (0, 0),
+            directory: ".".to_string(),
+            command: None,
+            environment_variables: Some(HashMap::from([
+                ("PORT".to_string(), "4000".to_string()),
+                ("BAD NAME".to_string(), "x".to_string()),
+            ])),
+        };
+
+        assert_eq!(
+            pane_environment(Some(&environment), Some(&pane)),
+            BTreeMap::from([
+                (
+                    "API_URL".to_string(),
+                    "https://staging.example.com".to_string()
+                ),
+                ("PORT".to_string(), "4000".to_string()),
+            ])
+        );
+        assert!(pane_environment(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_export_line() {
+        let vars = BTreeMap::from([
+            ("GREETING".to_string(), "it's $HOME".to_string()),
+            ("PORT".to_string(), "3000".to_string()),
+        ]);
+        assert_eq!(
+            export_line(&vars).unwrap(),
+            "export GREETING='it'\\''s $HOME' PORT='3000'"
+        );
+        assert_eq!(export_line(&BTreeMap::new()), None);
+    }
+}
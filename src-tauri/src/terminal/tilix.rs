@@ -0,0 +1,205 @@
+use super::backend::TerminalBackend;
+use super::plan::{LaunchPlan, PanePlan, SplitChild, SplitDirection, SplitNode, TabPlan};
+use super::{find_in_path, shell, write_layout_file};
+use crate::error::{CmdrError, Result};
+use serde_json::{json, Value};
+use std::process::Command;
+
+/// Size the generated sessions are laid out for. Tilix scales the panes
+/// to the real window, so only the proportions matter.
+const SESSION_WIDTH: u32 = 1280;
+const SESSION_HEIGHT: u32 = 800;
+
+/// Tilix, started from generated session files. Each tab of a window is a
+/// Tilix session, so splits are kept.
+pub struct Tilix;
+
+impl Tilix {
+    /// Start tilix
+    fn spawn(&self, args: &[String]) -> Result<()> {
+        log::info!("[Tilix] Starting tilix {}", args.join(" "));
+
+        Command::new("tilix").args(args).spawn().map_err(|e| {
+            log::error!("[Tilix] Failed to start tilix: {}", e);
+            CmdrError::Terminal(format!("Failed to start tilix: {}", e))
+        })?;
+
+        Ok(())
+    }
+}
+
+impl TerminalBackend for Tilix {
+    fn id(&self) -> &'static str {
+        "tilix"
+    }
+
+    fn name(&self) -> &'static str {
+        "Tilix"
+    }
+
+    fn is_available(&self) -> bool {
+        find_in_path("tilix").is_some()
+    }
+
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        log::info!(
+            "[Tilix] Generating sessions for workspace: {}",
+            plan.workspace_name
+        );
+
+        for (window_idx, window) in plan.windows.iter().enumerate() {
+            let mut args = Vec::new();
+            for (tab_idx, tab) in window.tabs.iter().enumerate() {
+                let session = generate_session(tab, &plan.workspace_name);
+                let file_name = format!(
+                    "{}-{}-{}.tilix.json",
+                    plan.session_name, window_idx, tab_idx
+                );
+                let path = write_layout_file(&file_name, &serde_json::to_string_pretty(&session)?)?;
+                args.push("--session".to_string());
+                args.push(path.to_string_lossy().to_string());
+            }
+            // Not tracked for stopping: Tilix hands the window to a running
+            // instance and exits, or becomes the instance every other Tilix
+            // window belongs to
+            self.spawn(&args)?;
+        }
+
+        log::info!("[Tilix] Workspace launched successfully");
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        self.spawn(&[])
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        let mut args = Vec::new();
+        if use_existing {
+            args.push("--action=app-new-session".to_string());
+        }
+        args.push(format!("--working-directory={}", pane.cwd));
+        args.push(format!("--command={}", pane_command(pane)));
+        self.spawn(&args)
+    }
+}
+
+/// Tilix session JSON for one tab
+pub fn generate_session(tab: &TabPlan, workspace_name: &str) -> Value {
+    json!({
+        "type": "Session",
+        "version": "1.0",
+        "uuid": uuid::Uuid::new_v4().to_string(),
+        "name": tab.title.as_deref().unwrap_or(workspace_name),
+        "synchronizedInput": false,
+        "width": SESSION_WIDTH,
+        "height": SESSION_HEIGHT,
+        "child": node(&tab.layout, SESSION_WIDTH, SESSION_HEIGHT),
+    })
+}
+
+fn node(node: &SplitNode, width: u32, height: u32) -> Value {
+    match node {
+        SplitNode::Pane(pane) => terminal(pane, width, height),
+        SplitNode::Split {
+            direction,
+            children,
+        } => paned(*direction, children, width, height),
+    }
+}
+
+/// Tilix splits are binary, so a split with more children becomes a chain:
+/// the first child, then a split of the rest sized to the space left
+fn paned(direction: SplitDirection, children: &[SplitChild], width: u32, height: u32) -> Value {
+    let (first, rest) = match children {
+        [only] => return node(&only.node, width, height),
+        [first, rest @ ..] => (first, rest),
+        [] => return Value::Null,
+    };
+
+    let total: u32 = children.iter().map(|c| c.size).sum();
+    let ratio = first.size as f64 / total.max(1) as f64;
+    let (orientation, position, first_child, second_child) = match direction {
+        SplitDirection::Horizontal => {
+            let position = (width as f64 * ratio).round() as u32;
+            (
+                0,
+                position,
+                node(&first.node, position, height),
+                paned(direction, rest, width - position, height),
+            )
+        }
+        SplitDirection::Vertical => {
+            let position = (height as f64 * ratio).round() as u32;
+            (
+                1,
+                position,
+                node(&first.node, width, position),
+                paned(direction, rest, width, height - position),
+            )
+        }
+    };
+
+    json!({
+        "type": "Paned",
+        "orientation": orientation,
+        "position": position,
+        "ratio": ratio,
+        "child1": first_child,
+        "child2": second_child,
+    })
+}
+
+fn terminal(pane: &PanePlan, width: u32, height: u32) -> Value {
+    let mut terminal = json!({
+        "type": "Terminal",
+        "uuid": uuid::Uuid::new_v4().to_string(),
+        "directory": pane.cwd,
+        "width": width,
+        "height": height,
+        "readOnly": false,
+        "synchronizedInput": true,
+    });
+    if pane.needs_script() {
+        terminal["overrideCommand"] = Value::String(pane_command(pane));
+    }
+    terminal
+}
+
+/// Tilix parses commands like a shell, so the script is passed quoted
+fn pane_command(pane: &PanePlan) -> String {
+    format!("sh -c {}", shell::quote(&pane.startup_script()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{pane, project, workspace};
+
+    #[test]
+    fn test_generate_session() {
+        let project = project("/work/app");
+        let workspace = workspace(vec![3], vec![pane((0, 2), "api", Some("npm run dev"))]);
+
+        let plan = LaunchPlan::build(&project, &workspace, None);
+        let session = generate_session(&plan.windows[0].tabs[0], &plan.workspace_name);
+
+        assert_eq!(session["name"], "Dev");
+        // Three columns become a split of the first column and the other two
+        let root = &session["child"];
+        assert_eq!(root["type"], "Paned");
+        assert_eq!(root["orientation"], 0);
+        assert_eq!(root["child1"]["type"], "Terminal");
+        assert_eq!(root["child1"]["directory"], "/work/app");
+        assert!(root["child1"].get("overrideCommand").is_none());
+        let rest = &root["child2"];
+        assert_eq!(rest["ratio"], 0.5);
+        assert_eq!(root["position"], 435);
+        assert_eq!(rest["child2"]["width"], 422);
+        assert_eq!(rest["child2"]["directory"], "/work/app/api");
+        assert_eq!(
+            rest["child2"]["overrideCommand"],
+            "sh -c 'npm run dev; exec \"${SHELL:-sh}\"'"
+        );
+    }
+}
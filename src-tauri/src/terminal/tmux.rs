@@ -0,0 +1,494 @@
+use super::backend::TerminalBackend;
+use super::capture::{self, CapturedPane};
+use super::plan::{LaunchPlan, PanePlan, SplitDirection, TabPlan};
+use super::{find_in_path, process, terminal_emulator};
+use crate::error::{CmdrError, Result};
+use std::process::Command;
+
+/// Size of the detached session before any client attaches, so that
+/// splitting a large grid does not run out of room
+const INITIAL_WIDTH: &str = "240";
+const INITIAL_HEIGHT: &str = "60";
+
+/// A tmux server, optionally isolated on its own socket (`tmux -L <name>`)
+pub struct Tmux {
+    socket: Option<String>,
+}
+
+impl Tmux {
+    pub fn new() -> Self {
+        Self { socket: None }
+    }
+
+    #[cfg(test)]
+    pub fn with_socket(socket: &str) -> Self {
+        Self {
+            socket: Some(socket.to_string()),
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut cmd = Command::new("tmux");
+        if let Some(socket) = &self.socket {
+            cmd.arg("-L").arg(socket);
+        }
+        cmd
+    }
+
+    /// Run a tmux subcommand and return its trimmed stdout
+    fn run(&self, args: &[&str]) -> Result<String> {
+        log::debug!("[tmux] tmux {}", args.join(" "));
+
+        let output = self.command().args(args).output().map_err(|e| {
+            log::error!("[tmux] Failed to execute tmux: {}", e);
+            CmdrError::Terminal(format!("Failed to execute tmux: {}", e))
+        })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::error!("[tmux] tmux error: {}", stderr);
+            return Err(CmdrError::Terminal(format!(
+                "tmux error: {}",
+                stderr.trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Create a detached tmux session for a launch plan and return its
+    /// name. tmux has no separate OS windows, so every tab of every window
+    /// becomes a tmux window.
+    pub fn create_session(&self, plan: &LaunchPlan) -> Result<String> {
+        let session = plan.session_name.clone();
+        log::info!(
+            "[tmux] Creating session {} for workspace: {}",
+            session,
+            plan.workspace_name
+        );
+
+        let tabs: Vec<&TabPlan> = plan.windows.iter().flat_map(|w| w.tabs.iter()).collect();
+        if tabs.is_empty() {
+            return Err(CmdrError::Terminal(format!(
+                "Workspace {} has no panes to launch",
+                plan.workspace_name
+            )));
+        }
+
+        let mut first_pane = None;
+        for (index, tab) in tabs.iter().enumerate() {
+            let (steps, panes) = tab.layout.split_steps();
+            log::debug!("[tmux] Tab {} has {} panes", index, panes.len());
+
+            let mut args: Vec<String> = if index == 0 {
+                [
+                    "new-session",
+                    "-d",
+                    "-s",
+                    &session,
+                    "-x",
+                    INITIAL_WIDTH,
+                    "-y",
+                    INITIAL_HEIGHT,
+                ]
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+            } else {
+                vec![
+                    "new-window".to_string(),
+                    "-t".to_string(),
+                    format!("{}:", session),
+                ]
+            };
+            if let Some(title) = &tab.title {
+                args.push("-n".to_string());
+                args.push(title.clone());
+            }
+            args.extend(["-P", "-F", "#{pane_id}"].iter().map(|s| s.to_string()));
+            args.extend(start_args(panes[0]));
+
+            let mut pane_ids = vec![self.run_owned(&args)?];
+            first_pane.get_or_insert_with(|| pane_ids[0].clone());
+
+            for step in &steps {
+                let mut args: Vec<String> = vec![
+                    "split-window".to_string(),
+                    match step.direction {
+                        SplitDirection::Horizontal => "-h",
+                        SplitDirection::Vertical => "-v",
+                    }
+                    .to_string(),
+                    "-t".to_string(),
+                    pane_ids[step.target].clone(),
+                    "-l".to_string(),
+                    format!("{}%", step.percent),
+                    "-P".to_string(),
+                    "-F".to_string(),
+                    "#{pane_id}".to_string(),
+                ];
+                args.extend(start_args(panes[step.pane]));
+                pane_ids.push(self.run_owned(&args)?);
+            }
+
+            // Send each pane its startup commands
+            for (pane_id, pane) in pane_ids.iter().zip(&panes) {
+                for command in &pane.commands {
+                    self.send_line(pane_id, command)?;
+                }
+            }
+        }
+
+        if let Some(pane_id) = first_pane {
+            self.run(&["select-window", "-t", &pane_id])?;
+            self.run(&["select-pane", "-t", &pane_id])?;
+        }
+
+        log::info!("[tmux] Session {} created", session);
+        Ok(session)
+    }
+
+    fn run_owned(&self, args: &[String]) -> Result<String> {
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        self.run(&args)
+    }
+
+    /// Type `line` into a pane and press Enter
+    fn send_line(&self, target: &str, line: &str) -> Result<()> {
+        self.run(&["send-keys", "-t", target, "-l", line])?;
+        self.run(&["send-keys", "-t", target, "Enter"])?;
+        Ok(())
+    }
+
+    /// Read the geometry, directory and program of every pane in a
+    /// session
+    pub fn capture_session(&self, session: &str) -> Result<Vec<CapturedPane>> {
+        log::info!("[tmux] Capturing session {}", session);
+
+        let output = self.run(&[
+            "list-panes",
+            "-s",
+            "-t",
+            &format!("={}", session),
+            "-F",
+            "#{window_index}\t#{pane_left}\t#{pane_top}\t#{pane_width}\t#{pane_height}\t#{pane_pid}\t#{pane_current_command}\t#{pane_current_path}\t#{window_name}",
+        ])?;
+
+        let table = process::process_table();
+        let mut panes = Vec::new();
+        for line in output.lines() {
+            let fields: Vec<&str> = line.splitn(9, '\t').collect();
+            let [window, left, top, width, height, pid, command, directory, window_name] =
+                fields[..]
+            else {
+                log::warn!("[tmux] Skipping unexpected pane line: {}", line);
+                continue;
+            };
+            let number = |field: &str| {
+                field
+                    .parse::<u32>()
+                    .map_err(|_| CmdrError::Terminal(format!("Unexpected tmux output: {}", line)))
+            };
+
+            panes.push(CapturedPane {
+                window: number(window)?,
+                window_name: window_name.to_string(),
+                left: number(left)?,
+                top: number(top)?,
+                width: number(width)?,
+                height: number(height)?,
+                directory: directory.to_string(),
+                command: pane_command(&table, number(pid)?, command),
+            });
+        }
+
+        log::info!("[tmux] Captured {} panes", panes.len());
+        Ok(panes)
+    }
+
+    /// Whether a tmux server is running with at least one session
+    fn has_sessions(&self) -> bool {
+        self.run(&["list-sessions"]).is_ok()
+    }
+
+    /// Bring the session to the foreground: switch to it when already
+    /// inside tmux, otherwise open a terminal emulator attached to it
+    pub fn attach(&self, session: &str) -> Result<()> {
+        if std::env::var_os("TMUX").is_some() {
+            log::info!("[tmux] Switching client to session {}", session);
+            self.run(&["switch-client", "-t", session])?;
+            return Ok(());
+        }
+
+        let emulator = terminal_emulator();
+        log::info!("[tmux] Attaching to session {} with {}", session, emulator);
+
+        let mut cmd = Command::new(&emulator);
+        cmd.arg("-e").arg("tmux");
+        if let Some(socket) = &self.socket {
+            cmd.arg("-L").arg(socket);
+        }
+        cmd.arg("attach-session").arg("-t").arg(session);
+
+        cmd.spawn().map_err(|e| {
+            log::error!("[tmux] Failed to open {}: {}", emulator, e);
+            CmdrError::Terminal(format!(
+                "Session {} is running but {} could not be opened to attach: {}",
+                session, emulator, e
+            ))
+        })?;
+
+        Ok(())
+    }
+}
+
+impl TerminalBackend for Tmux {
+    fn id(&self) -> &'static str {
+        "tmux"
+    }
+
+    fn name(&self) -> &'static str {
+        "tmux"
+    }
+
+    fn is_available(&self) -> bool {
+        find_in_path("tmux").is_some()
+    }
+
+    fn is_running(&self) -> bool {
+        self.has_sessions()
+    }
+
+    fn running_sessions(&self) -> Vec<String> {
+        self.run(&["list-sessions", "-F", "#{session_name}"])
+            .map(|out| out.lines().map(|line| line.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    fn attach_session(&self, session: &str) -> Result<()> {
+        self.attach(session)
+    }
+
+    fn session_pids(&self, session: &str) -> Vec<u32> {
+        self.run(&[
+            "list-panes",
+            "-s",
+            "-t",
+            &format!("={}", session),
+            "-F",
+            "#{pane_pid}",
+        ])
+        .map(|out| out.lines().filter_map(|line| line.parse().ok()).collect())
+        .unwrap_or_default()
+    }
+
+    fn kill_session(&self, session: &str) -> Result<()> {
+        log::info!("[tmux] Killing session {}", session);
+        self.run(&["kill-session", "-t", &format!("={}", session)])?;
+        Ok(())
+    }
+
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        let session = self.create_session(plan)?;
+        self.attach(&session)?;
+        log::info!(
+            "[tmux] Workspace launched successfully in session {}",
+            session
+        );
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        let session = self.run(&["new-session", "-d", "-P", "-F", "#{session_name}"])?;
+        self.attach(&session)
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        let args = start_args(pane);
+
+        // Reuse the running server as a new window, otherwise start a session
+        if use_existing && self.has_sessions() {
+            let mut window_args = vec![
+                "new-window".to_string(),
+                "-P".to_string(),
+                "-F".to_string(),
+                "#{pane_id}".to_string(),
+            ];
+            window_args.extend(args);
+            let pane_id = self.run_owned(&window_args)?;
+            for command in &pane.commands {
+                self.send_line(&pane_id, command)?;
+            }
+            return Ok(());
+        }
+
+        let mut session_args = vec![
+            "new-session".to_string(),
+            "-d".to_string(),
+            "-P".to_string(),
+            "-F".to_string(),
+            "#{session_name} #{pane_id}".to_string(),
+        ];
+        session_args.extend(args);
+        let output = self.run_owned(&session_args)?;
+        let (session, pane_id) = output
+            .split_once(' ')
+            .ok_or_else(|| CmdrError::Terminal(format!("Unexpected tmux output: {}", output)))?;
+        for command in &pane.commands {
+            self.send_line(pane_id, command)?;
+        }
+        self.attach(session)
+    }
+}
+
+/// Start directory and environment (`-c`, `-e`) for a pane. tmux sets the
+/// variables on the pane's shell directly, so nothing has to be typed into it.
+fn start_args(pane: &PanePlan) -> Vec<String> {
+    let mut args = vec!["-c".to_string(), pane.cwd.clone()];
+    for (name, value) in &pane.env {
+        args.push("-e".to_string());
+        args.push(format!("{}={}", name, value));
+    }
+    args
+}
+
+/// What a captured pane runs: the full command line of the program in
+/// front of the pane's shell, or tmux's short name for it when that cannot
+/// be read. Idle shells run nothing.
+fn pane_command(table: &[(u32, u32)], shell_pid: u32, current: &str) -> Option<String> {
+    if current.is_empty() || capture::is_shell(current) {
+        return None;
+    }
+
+    table
+        .iter()
+        .find(|(_, ppid)| *ppid == shell_pid)
+        .and_then(|(pid, _)| process::command_line(*pid))
+        .or_else(|| Some(current.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{environment, layout, pane, project, workspace};
+    use crate::config::WorkspaceTab;
+    use std::collections::HashMap;
+
+    /// Runs against a private tmux server; skipped when tmux is not installed
+    #[test]
+    fn test_create_session_on_tmux_server() {
+        if Command::new("tmux").arg("-V").output().is_err() {
+            eprintln!("tmux not installed, skipping");
+            return;
+        }
+
+        let root = std::env::temp_dir().join(format!("cmdr-tmux-test-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("backend")).unwrap();
+        let root = root.canonicalize().unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        let tmux = Tmux::with_socket(&format!("cmdr-test-{}", std::process::id()));
+        let mut backend = pane(
+            (0, 1),
+            "backend",
+            Some("printf '%s' \"$GREETING $PORT\" > env.txt"),
+        );
+        backend.environment_variables =
+            Some(HashMap::from([("PORT".to_string(), "4000".to_string())]));
+        let mut ws = workspace(vec![2, 1], vec![backend, pane((1, 0), ".", None)]);
+        ws.title = Some("servers".to_string());
+        ws.tabs = vec![WorkspaceTab {
+            title: Some("logs".to_string()),
+            window: 1,
+            layout: layout(vec![2]),
+            panes: Vec::new(),
+        }];
+        let environment = environment("dev", &[("GREETING", "it's me")]);
+
+        let plan = LaunchPlan::build(&project(&root_str), &ws, Some(&environment));
+        let session = plan.session_name.as_str();
+        let result = tmux.create_session(&plan);
+
+        // The command runs once the pane's shell has started, which can take
+        // a few seconds with a heavy shell profile
+        let env_file = root.join("backend").join("env.txt");
+        let mut env_output = String::new();
+        for _ in 0..200 {
+            env_output = std::fs::read_to_string(&env_file).unwrap_or_default();
+            if !env_output.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+
+        let panes = tmux.run(&[
+            "list-panes",
+            "-t",
+            session,
+            "-F",
+            "#{pane_top} #{pane_left} #{pane_current_path}",
+        ]);
+        let windows = tmux.run(&[
+            "list-windows",
+            "-t",
+            session,
+            "-F",
+            "#{window_name} #{window_panes}",
+        ]);
+        let shells = tmux.session_pids(session);
+        let captured = tmux.capture_session(session);
+        let running = tmux.running_sessions();
+        let killed = tmux.kill_session(session);
+        let after_kill = tmux.running_sessions();
+        let _ = tmux.run(&["kill-server"]);
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(result.unwrap(), session);
+        // Every tab becomes a tmux window, whichever window it was in
+        assert_eq!(windows.unwrap(), "servers 3\nlogs 2");
+        assert_eq!(env_output, "it's me 4000");
+        assert_eq!(shells.len(), 5);
+        let captured = captured.unwrap();
+        assert_eq!(captured.len(), 5);
+        let imported = capture::build_workspace(&project(&root_str), "Imported", &captured);
+        assert!(crate::config::validate_workspace(&imported).is_empty());
+        assert_eq!(imported.title.as_deref(), Some("servers"));
+        assert_eq!(imported.tabs.len(), 1);
+        assert_eq!(imported.tabs[0].title.as_deref(), Some("logs"));
+        let directories: Vec<&str> = imported
+            .panes
+            .iter()
+            .map(|p| p.directory.as_str())
+            .collect();
+        assert_eq!(directories, vec![".", "backend", "."]);
+        assert!(imported.panes.iter().all(|p| p.command.is_none()));
+        assert_eq!(running, vec![session]);
+        assert!(killed.is_ok());
+        assert!(after_kill.is_empty());
+        let mut panes: Vec<(u32, u32, String)> = panes
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let mut parts = line.splitn(3, ' ');
+                (
+                    parts.next().unwrap().parse().unwrap(),
+                    parts.next().unwrap().parse().unwrap(),
+                    parts.next().unwrap().to_string(),
+                )
+            })
+            .collect();
+        panes.sort();
+
+        assert_eq!(panes.len(), 3);
+        // Top row has two panes side by side, bottom row spans the width
+        assert_eq!(panes[0].0, 0);
+        assert_eq!(panes[0].1, 0);
+        assert_eq!(panes[0].2, root_str);
+        assert_eq!(panes[1].0, 0);
+        assert!(panes[1].1 > 0);
+        assert_eq!(panes[1].2, format!("{}/backend", root_str));
+        assert!(panes[2].0 > 0);
+        assert_eq!(panes[2].1, 0);
+        assert_eq!(panes[2].2, root_str);
+    }
+}
@@ -0,0 +1,435 @@
+use super::backend::TerminalBackend;
+use super::plan::{LaunchPlan, PanePlan, SplitDirection, SplitStep};
+use super::{application_installed, find_in_path};
+use crate::error::{CmdrError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// Where the wezterm binary lives inside its macOS application bundle
+const MACOS_BINARY: &str = "/Applications/WezTerm.app/Contents/MacOS/wezterm";
+
+/// File in the config directory that remembers the panes of launched
+/// workspaces
+const PANES_FILE: &str = "wezterm-panes.json";
+
+/// Held from loading the pane registry until it is saved, so that launches
+/// and commands running at the same time don't drop each other's panes
+static PANES_LOCK: Mutex<()> = Mutex::new(());
+
+/// How long to wait for a freshly started WezTerm to accept `wezterm cli`
+const STARTUP_ATTEMPTS: u32 = 50;
+const STARTUP_POLL: Duration = Duration::from_millis(100);
+
+/// WezTerm, driven through `wezterm cli`
+pub struct WezTerm;
+
+/// Pane ids of the workspaces launched in WezTerm, keyed by session name,
+/// so that relaunching a workspace or running a command can find them again
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaneRegistry {
+    #[serde(default)]
+    pub sessions: BTreeMap<String, Vec<u64>>,
+    /// Session launched most recently, which commands are sent to
+    #[serde(default, alias = "last_session")]
+    pub last_session: Option<String>,
+}
+
+impl PaneRegistry {
+    fn lock() -> MutexGuard<'static, ()> {
+        PANES_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn load() -> Self {
+        let path = match crate::config::get_config_dir() {
+            Ok(dir) => dir.join(PANES_FILE),
+            Err(_) => return Self::default(),
+        };
+        std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = crate::config::get_config_dir()?.join(PANES_FILE);
+        log::debug!("[WezTerm] Saving pane ids to: {:?}", path);
+        // Renamed over the old file so that it is never read half written
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    fn record(&mut self, session: &str, panes: Vec<u64>) {
+        self.sessions.insert(session.to_string(), panes);
+        self.last_session = Some(session.to_string());
+    }
+
+    /// Drop sessions whose panes have all been closed
+    fn retain_alive(&mut self, alive: &[u64]) {
+        self.sessions.retain(|_, panes| {
+            panes.retain(|id| alive.contains(id));
+            !panes.is_empty()
+        });
+        if let Some(last) = &self.last_session {
+            if !self.sessions.contains_key(last) {
+                self.last_session = None;
+            }
+        }
+    }
+
+    /// Drop the panes that are no longer open, all of them when WezTerm is
+    /// not running
+    fn prune(&mut self, live: Option<Vec<u64>>) {
+        self.retain_alive(live.as_deref().unwrap_or_default());
+    }
+
+    /// First pane of a session that is still open
+    fn first_pane(&self, session: &str) -> Option<u64> {
+        self.sessions
+            .get(session)
+            .and_then(|panes| panes.first().copied())
+    }
+}
+
+impl WezTerm {
+    fn binary(&self) -> String {
+        if find_in_path("wezterm").is_none() && cfg!(target_os = "macos") {
+            MACOS_BINARY.to_string()
+        } else {
+            "wezterm".to_string()
+        }
+    }
+
+    /// Run a `wezterm cli` subcommand and return its trimmed stdout. The
+    /// CLI is not allowed to start a headless mux server of its own.
+    fn cli(&self, args: &[&str]) -> Result<String> {
+        log::debug!("[WezTerm] wezterm cli {}", args.join(" "));
+
+        let output = Command::new(self.binary())
+            .args(["cli", "--no-auto-start"])
+            .args(args)
+            .output()
+            .map_err(|e| {
+                log::error!("[WezTerm] Failed to execute wezterm: {}", e);
+                CmdrError::Terminal(format!("Failed to execute wezterm: {}", e))
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::error!("[WezTerm] wezterm error: {}", stderr);
+            return Err(CmdrError::Terminal(format!(
+                "wezterm error: {}",
+                stderr.trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn cli_owned(&self, args: &[String]) -> Result<String> {
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        self.cli(&args)
+    }
+
+    /// Run a subcommand that prints the id of the pane it created
+    fn cli_pane(&self, args: &[String]) -> Result<u64> {
+        let output = self.cli_owned(args)?;
+        output
+            .parse()
+            .map_err(|_| CmdrError::Terminal(format!("Unexpected wezterm output: {}", output)))
+    }
+
+    /// Ids of every open pane, or `None` when WezTerm is not running
+    fn live_panes(&self) -> Option<Vec<u64>> {
+        let output = self.cli(&["list", "--format", "json"]).ok()?;
+        let panes: Vec<serde_json::Value> = serde_json::from_str(&output).ok()?;
+        Some(panes.iter().filter_map(|p| p["pane_id"].as_u64()).collect())
+    }
+
+    /// Make sure a WezTerm GUI is running. When one has to be started, it
+    /// opens in `cwd` and the id of its only pane is returned, so that the
+    /// caller can use that window instead of opening another.
+    fn ensure_running(&self, cwd: &str) -> Result<Option<u64>> {
+        if self.live_panes().is_some() {
+            return Ok(None);
+        }
+
+        log::info!("[WezTerm] Starting WezTerm in {}", cwd);
+        Command::new(self.binary())
+            .args(["start", "--cwd", cwd])
+            .spawn()
+            .map_err(|e| {
+                log::error!("[WezTerm] Failed to start WezTerm: {}", e);
+                CmdrError::Terminal(format!("Failed to start WezTerm: {}", e))
+            })?;
+
+        for _ in 0..STARTUP_ATTEMPTS {
+            thread::sleep(STARTUP_POLL);
+            if let Some(panes) = self.live_panes() {
+                if let Some(&pane) = panes.first() {
+                    return Ok(Some(pane));
+                }
+            }
+        }
+
+        Err(CmdrError::Terminal(
+            "WezTerm was started but did not accept commands in time".to_string(),
+        ))
+    }
+
+    /// Type each of the pane's shell lines into it
+    fn send_lines(&self, pane_id: u64, pane: &PanePlan) -> Result<()> {
+        for line in pane.shell_lines() {
+            self.cli_owned(&send_text_args(pane_id, &line))?;
+        }
+        Ok(())
+    }
+
+    /// Open every window, tab and pane of a plan and return the pane ids
+    /// in plan order
+    fn create_panes(&self, plan: &LaunchPlan) -> Result<Vec<u64>> {
+        let first_cwd = plan.panes().first().map(|p| p.cwd.clone()).ok_or_else(|| {
+            CmdrError::Terminal(format!(
+                "Workspace {} has no panes to launch",
+                plan.workspace_name
+            ))
+        })?;
+        let mut started = self.ensure_running(&first_cwd)?;
+
+        let mut pane_ids = Vec::new();
+        for window in &plan.windows {
+            let mut window_pane: Option<u64> = None;
+
+            for tab in &window.tabs {
+                let (steps, panes) = tab.layout.split_steps();
+
+                let first = match (started.take(), window_pane) {
+                    (Some(pane), _) => pane,
+                    (None, None) => self.cli_pane(&spawn_args(None, &panes[0].cwd))?,
+                    (None, Some(existing)) => {
+                        self.cli_pane(&spawn_args(Some(existing), &panes[0].cwd))?
+                    }
+                };
+                window_pane.get_or_insert(first);
+
+                if let Some(title) = &tab.title {
+                    let id = first.to_string();
+                    if let Err(e) = self.cli(&["set-tab-title", "--pane-id", &id, title]) {
+                        log::warn!("[WezTerm] Could not set tab title: {}", e);
+                    }
+                }
+
+                let mut tab_ids = vec![first];
+                for step in &steps {
+                    let args = split_args(step, tab_ids[step.target], &panes[step.pane].cwd);
+                    tab_ids.push(self.cli_pane(&args)?);
+                }
+
+                for (pane_id, pane) in tab_ids.iter().zip(&panes) {
+                    self.send_lines(*pane_id, pane)?;
+                }
+                pane_ids.extend(tab_ids);
+            }
+        }
+
+        Ok(pane_ids)
+    }
+}
+
+impl TerminalBackend for WezTerm {
+    fn id(&self) -> &'static str {
+        "wezterm"
+    }
+
+    fn name(&self) -> &'static str {
+        "WezTerm"
+    }
+
+    fn is_available(&self) -> bool {
+        find_in_path("wezterm").is_some()
+            || (cfg!(target_os = "macos") && application_installed("WezTerm.app"))
+    }
+
+    fn is_running(&self) -> bool {
+        self.live_panes().is_some()
+    }
+
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        let session = &plan.session_name;
+        let _guard = PaneRegistry::lock();
+        let mut registry = PaneRegistry::load();
+        registry.prune(self.live_panes());
+
+        // Bring an already launched workspace forward instead of opening it twice
+        if let Some(pane) = registry.first_pane(session) {
+            log::info!(
+                "[WezTerm] Workspace {} is already open, activating it",
+                plan.workspace_name
+            );
+            self.cli(&["activate-pane", "--pane-id", &pane.to_string()])?;
+            registry.last_session = Some(session.clone());
+            return registry.save();
+        }
+
+        log::info!("[WezTerm] Launching workspace: {}", plan.workspace_name);
+        let pane_ids = self.create_panes(plan)?;
+        log::debug!("[WezTerm] Session {} has panes {:?}", session, pane_ids);
+
+        registry.record(session, pane_ids);
+        registry.save()?;
+
+        log::info!("[WezTerm] Workspace launched successfully");
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        if self.live_panes().is_some() {
+            self.cli(&["spawn", "--new-window"])?;
+            return Ok(());
+        }
+        let cwd = dirs::home_dir()
+            .map(|home| home.to_string_lossy().to_string())
+            .unwrap_or_else(|| ".".to_string());
+        self.ensure_running(&cwd).map(|_| ())
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        let _guard = PaneRegistry::lock();
+        let mut registry = PaneRegistry::load();
+        registry.prune(self.live_panes());
+
+        // Open a tab in the window of the last launched workspace
+        let existing = registry
+            .last_session
+            .as_deref()
+            .and_then(|session| registry.first_pane(session))
+            .filter(|_| use_existing);
+
+        let pane_id = match existing {
+            Some(window_pane) => self.cli_pane(&spawn_args(Some(window_pane), &pane.cwd))?,
+            None => match self.ensure_running(&pane.cwd)? {
+                Some(started) => started,
+                None => self.cli_pane(&spawn_args(None, &pane.cwd))?,
+            },
+        };
+        self.send_lines(pane_id, pane)?;
+
+        let _ = registry.save();
+        Ok(())
+    }
+}
+
+/// Arguments for `spawn`: a tab in the window of `window_pane`, or a new
+/// window when there is none
+fn spawn_args(window_pane: Option<u64>, cwd: &str) -> Vec<String> {
+    let mut args = vec!["spawn".to_string()];
+    match window_pane {
+        Some(pane) => {
+            args.push("--pane-id".to_string());
+            args.push(pane.to_string());
+        }
+        None => args.push("--new-window".to_string()),
+    }
+    args.push("--cwd".to_string());
+    args.push(cwd.to_string());
+    args
+}
+
+/// Arguments for `split-pane`. The percentage is the new pane's share of
+/// the pane being split, as with tmux.
+fn split_args(step: &SplitStep, target: u64, cwd: &str) -> Vec<String> {
+    vec![
+        "split-pane".to_string(),
+        "--pane-id".to_string(),
+        target.to_string(),
+        match step.direction {
+            SplitDirection::Horizontal => "--right",
+            SplitDirection::Vertical => "--bottom",
+        }
+        .to_string(),
+        "--percent".to_string(),
+        step.percent.to_string(),
+        "--cwd".to_string(),
+        cwd.to_string(),
+    ]
+}
+
+/// Arguments for `send-text` that type `line` and press Enter
+fn send_text_args(pane_id: u64, line: &str) -> Vec<String> {
+    vec![
+        "send-text".to_string(),
+        "--pane-id".to_string(),
+        pane_id.to_string(),
+        "--no-paste".to_string(),
+        format!("{}\r", line),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_args() {
+        let step = SplitStep {
+            target: 0,
+            pane: 1,
+            direction: SplitDirection::Vertical,
+            percent: 33,
+        };
+        assert_eq!(
+            split_args(&step, 7, "/work/my app").join(" "),
+            "split-pane --pane-id 7 --bottom --percent 33 --cwd /work/my app"
+        );
+        assert_eq!(
+            spawn_args(None, "/work").join(" "),
+            "spawn --new-window --cwd /work"
+        );
+        assert_eq!(
+            spawn_args(Some(3), "/work").join(" "),
+            "spawn --pane-id 3 --cwd /work"
+        );
+        assert_eq!(send_text_args(3, "npm start")[4], "npm start\r");
+    }
+
+    #[test]
+    fn test_pane_registry() {
+        let mut registry = PaneRegistry::default();
+        registry.record("cmdr-app-dev", vec![4, 5, 6]);
+        registry.record("cmdr-app-logs", vec![9]);
+        assert_eq!(registry.last_session.as_deref(), Some("cmdr-app-logs"));
+
+        // Closed panes are forgotten, along with sessions that have none left
+        registry.retain_alive(&[5, 6, 10]);
+        assert_eq!(registry.first_pane("cmdr-app-dev"), Some(5));
+        assert_eq!(registry.first_pane("cmdr-app-logs"), None);
+        assert_eq!(registry.last_session, None);
+
+        let json = serde_json::to_string(&registry).unwrap();
+        assert_eq!(
+            json,
+            r#"{"sessions":{"cmdr-app-dev":[5,6]},"lastSession":null}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<PaneRegistry>(&json).unwrap(),
+            registry
+        );
+    }
+
+    #[test]
+    fn test_pane_registry_not_running() {
+        let mut registry = PaneRegistry::default();
+        registry.record("cmdr-app-dev", vec![4, 5]);
+
+        // Without a running WezTerm none of the recorded panes exist
+        registry.prune(None);
+        assert_eq!(registry.first_pane("cmdr-app-dev"), None);
+        assert_eq!(registry, PaneRegistry::default());
+    }
+}
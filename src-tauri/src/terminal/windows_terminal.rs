@@ -0,0 +1,350 @@
+use super::backend::TerminalBackend;
+use super::find_in_path;
+use super::plan::{LaunchPlan, PanePlan, SplitDirection};
+use crate::error::{CmdrError, Result};
+use std::process::Command;
+
+/// Windows Terminal, driven through `wt.exe` command lines
+pub struct WindowsTerminal;
+
+/// PowerShell that runs the variables and commands of a pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerShell {
+    /// PowerShell 7 or later, `pwsh.exe`
+    Core,
+    /// Windows PowerShell 5.1, which has no `&&`
+    Windows,
+}
+
+impl PowerShell {
+    /// PowerShell 7 when it is installed, Windows PowerShell otherwise
+    fn detect() -> Self {
+        if find_in_path("pwsh.exe").is_some() {
+            PowerShell::Core
+        } else {
+            PowerShell::Windows
+        }
+    }
+
+    fn binary(self) -> &'static str {
+        match self {
+            PowerShell::Core => "pwsh.exe",
+            PowerShell::Windows => "powershell.exe",
+        }
+    }
+}
+
+impl WindowsTerminal {
+    fn spawn(&self, args: &[String]) -> Result<()> {
+        log::info!("[Windows Terminal] Starting wt.exe {}", args.join(" "));
+
+        Command::new("wt.exe").args(args).spawn().map_err(|e| {
+            log::error!("[Windows Terminal] Failed to start wt.exe: {}", e);
+            CmdrError::Terminal(format!("Failed to start Windows Terminal: {}", e))
+        })?;
+
+        Ok(())
+    }
+}
+
+impl TerminalBackend for WindowsTerminal {
+    fn id(&self) -> &'static str {
+        "windows_terminal"
+    }
+
+    fn name(&self) -> &'static str {
+        "Windows Terminal"
+    }
+
+    fn is_available(&self) -> bool {
+        cfg!(target_os = "windows") && find_in_path("wt.exe").is_some()
+    }
+
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        log::info!(
+            "[Windows Terminal] Launching workspace: {}",
+            plan.workspace_name
+        );
+
+        for args in generate_args(plan, PowerShell::detect()) {
+            self.spawn(&args)?;
+        }
+
+        log::info!("[Windows Terminal] Workspace launched successfully");
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        self.spawn(&["-w".to_string(), "new".to_string()])
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        // Window 0 is the most recently used one
+        let window = if use_existing { "0" } else { "new" };
+        let mut args = vec!["-w".to_string(), window.to_string(), "new-tab".to_string()];
+        args.extend(pane_args(pane, PowerShell::detect()));
+        self.spawn(&args)
+    }
+}
+
+/// `wt.exe` arguments for a launch plan, one list per window.
+///
+/// Each tab is a `new-tab` followed by its `split-pane`s. A split acts on
+/// the focused pane, which is the one created last, so `focus-pane` is only
+/// needed when a split targets an earlier pane.
+pub fn generate_args(plan: &LaunchPlan, shell: PowerShell) -> Vec<Vec<String>> {
+    plan.windows
+        .iter()
+        .map(|window| {
+            let mut args = vec!["-w".to_string(), "new".to_string()];
+
+            for (tab_idx, tab) in window.tabs.iter().enumerate() {
+                let (steps, panes) = tab.layout.split_steps();
+
+                if tab_idx > 0 {
+                    args.push(";".to_string());
+                }
+                args.push("new-tab".to_string());
+                if let Some(title) = &tab.title {
+                    args.push("--title".to_string());
+                    args.push(escape(title));
+                }
+                args.extend(pane_args(panes[0], shell));
+
+                for step in &steps {
+                    if step.target + 1 != step.pane {
+                        args.extend([";", "focus-pane", "-t"].iter().map(|s| s.to_string()));
+                        args.push(step.target.to_string());
+                    }
+                    args.push(";".to_string());
+                    args.push("split-pane".to_string());
+                    // wt names splits after the divider, like iTerm
+                    args.push(
+                        match step.direction {
+                            SplitDirection::Horizontal => "-V",
+                            SplitDirection::Vertical => "-H",
+                        }
+                        .to_string(),
+                    );
+                    args.push("--size".to_string());
+                    args.push(format!("{}", step.percent as f64 / 100.0));
+                    args.extend(pane_args(panes[step.pane], shell));
+                }
+            }
+
+            args
+        })
+        .collect()
+}
+
+/// Start directory of a pane, and a PowerShell running its variables and
+/// commands when it has any
+fn pane_args(pane: &PanePlan, shell: PowerShell) -> Vec<String> {
+    let mut args = vec!["-d".to_string(), escape(&pane.cwd)];
+    if pane.needs_script() {
+        args.push(shell.binary().to_string());
+        args.push("-NoExit".to_string());
+        args.push("-Command".to_string());
+        args.push(escape(&powershell_script(pane, shell)));
+    }
+    args
+}
+
+/// The pane's variables and commands as one PowerShell command line
+fn powershell_script(pane: &PanePlan, shell: PowerShell) -> String {
+    let mut lines: Vec<String> = pane
+        .env
+        .iter()
+        .map(|(name, value)| format!("$env:{}={}", name, powershell_quote(value)))
+        .collect();
+    lines.extend(pane.commands.iter().map(|command| match shell {
+        PowerShell::Core => command.clone(),
+        PowerShell::Windows => without_and_operator(command),
+    }));
+    lines.join("; ")
+}
+
+/// Rewrite `a && b` as `a; if ($?) { b }` for Windows PowerShell, leaving
+/// `&&` inside quotes alone
+fn without_and_operator(command: &str) -> String {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    let mut chars = command.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '&') if chars.peek().is_some_and(|&(_, next)| next == '&') => {
+                chars.next();
+                parts.push(command[start..i].trim());
+                start = i + 2;
+            }
+            _ => {}
+        }
+    }
+    parts.push(command[start..].trim());
+
+    let last = parts.pop().unwrap_or_default().to_string();
+    parts.into_iter().rev().fold(last, |rest, part| {
+        format!("{}; if ($?) {{ {} }}", part, rest)
+    })
+}
+
+/// Quote a string for PowerShell
+fn powershell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// `;` separates wt.exe subcommands, so one that belongs to an argument
+/// has to be escaped
+fn escape(arg: &str) -> String {
+    arg.replace(';', "\\;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{self, environment, layout, project};
+    use crate::config::{LayoutNode, Pane, Workspace, WorkspaceLayout, WorkspaceTab};
+    use std::path::Path;
+
+    fn pane(directory: &str, command: Option<&str>) -> Pane {
+        fixtures::pane((0, 0), directory, command)
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("npm test; npm run lint"), "npm test\\; npm run lint");
+        assert_eq!(powershell_quote("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn test_and_operator() {
+        let pane = PanePlan::for_command(
+            &project("C:\\work\\app"),
+            "npm ci && npm test && echo 'a && b'",
+            None,
+        );
+
+        assert_eq!(
+            pane_args(&pane, PowerShell::Core)[2..],
+            [
+                "pwsh.exe",
+                "-NoExit",
+                "-Command",
+                "npm ci && npm test && echo 'a && b'"
+            ]
+        );
+        assert_eq!(
+            pane_args(&pane, PowerShell::Windows)[2..],
+            [
+                "powershell.exe",
+                "-NoExit",
+                "-Command",
+                "npm ci\\; if ($?) { npm test\\; if ($?) { echo 'a && b' } }"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_args() {
+        let project = project("C:\\work\\app");
+        let leaf = |pane: usize| LayoutNode::Pane {
+            pane: Some(pane),
+            size: None,
+        };
+        let workspace = Workspace {
+            // Pane 0 above pane 2 on the left, pane 1 on the right
+            layout: WorkspaceLayout {
+                rows: 0,
+                columns: Vec::new(),
+                split: Some(LayoutNode::Split {
+                    direction: SplitDirection::Horizontal,
+                    size: None,
+                    children: vec![
+                        LayoutNode::Split {
+                            direction: SplitDirection::Vertical,
+                            size: Some(70),
+                            children: vec![leaf(0), leaf(2)],
+                        },
+                        leaf(1),
+                    ],
+                }),
+            },
+            panes: vec![
+                pane(".", Some("npm run build; npm start")),
+                pane(".", None),
+                pane("logs", None),
+            ],
+            title: Some("dev; servers".to_string()),
+            tabs: vec![WorkspaceTab {
+                title: None,
+                window: 0,
+                layout: layout(vec![1]),
+                panes: Vec::new(),
+            }],
+            ..fixtures::workspace(Vec::new(), Vec::new())
+        };
+        let environment = environment("dev", &[("STAGE", "it's dev")]);
+
+        let plan = LaunchPlan::build(&project, &workspace, Some(&environment));
+        let args = generate_args(&plan, PowerShell::Windows);
+
+        assert_eq!(args.len(), 1);
+        let script = "$env:STAGE='it''s dev'";
+        let logs = Path::new("C:\\work\\app").join("logs");
+        let expected: Vec<String> = [
+            "-w",
+            "new",
+            "new-tab",
+            "--title",
+            "dev\\; servers",
+            "-d",
+            "C:\\work\\app",
+            "powershell.exe",
+            "-NoExit",
+            "-Command",
+            "$env:STAGE='it''s dev'\\; npm run build\\; npm start",
+            ";",
+            "split-pane",
+            "-V",
+            "--size",
+            "0.3",
+            "-d",
+            "C:\\work\\app",
+            "powershell.exe",
+            "-NoExit",
+            "-Command",
+            script,
+            ";",
+            "focus-pane",
+            "-t",
+            "0",
+            ";",
+            "split-pane",
+            "-H",
+            "--size",
+            "0.5",
+            "-d",
+            &logs.to_string_lossy(),
+            "powershell.exe",
+            "-NoExit",
+            "-Command",
+            script,
+            ";",
+            "new-tab",
+            "-d",
+            "C:\\work\\app",
+            "powershell.exe",
+            "-NoExit",
+            "-Command",
+            script,
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(args[0], expected);
+    }
+}
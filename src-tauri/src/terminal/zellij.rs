@@ -0,0 +1,367 @@
+use super::backend::TerminalBackend;
+use super::plan::{LaunchPlan, PanePlan, SplitDirection, SplitNode};
+use super::{find_in_path, terminal_emulator, write_layout_file};
+use crate::error::{CmdrError, Result};
+use std::process::Command;
+
+/// zellij, started from a generated KDL layout
+pub struct Zellij;
+
+impl Zellij {
+    /// Run a zellij subcommand and return its trimmed stdout
+    fn run(&self, args: &[&str]) -> Result<String> {
+        log::debug!("[zellij] zellij {}", args.join(" "));
+
+        let output = Command::new("zellij").args(args).output().map_err(|e| {
+            log::error!("[zellij] Failed to execute zellij: {}", e);
+            CmdrError::Terminal(format!("Failed to execute zellij: {}", e))
+        })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::error!("[zellij] zellij error: {}", stderr);
+            return Err(CmdrError::Terminal(format!(
+                "zellij error: {}",
+                stderr.trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Names of the running sessions. Exited sessions that zellij keeps
+    /// around for resurrection are left out.
+    fn sessions(&self) -> Vec<String> {
+        self.run(&["list-sessions", "--no-formatting"])
+            .map(|out| parse_sessions(&out))
+            .unwrap_or_default()
+    }
+
+    /// zellij is a full-screen client, so it always runs in a new window
+    /// of the user's terminal emulator
+    fn open_client(&self, args: &[&str]) -> Result<()> {
+        let emulator = terminal_emulator();
+        log::info!(
+            "[zellij] Opening zellij {} with {}",
+            args.join(" "),
+            emulator
+        );
+
+        Command::new(&emulator)
+            .arg("-e")
+            .arg("zellij")
+            .args(args)
+            .spawn()
+            .map_err(|e| {
+                log::error!("[zellij] Failed to open {}: {}", emulator, e);
+                CmdrError::Terminal(format!("Could not open {} to run zellij: {}", emulator, e))
+            })?;
+
+        Ok(())
+    }
+}
+
+impl TerminalBackend for Zellij {
+    fn id(&self) -> &'static str {
+        "zellij"
+    }
+
+    fn name(&self) -> &'static str {
+        "zellij"
+    }
+
+    fn is_available(&self) -> bool {
+        find_in_path("zellij").is_some()
+    }
+
+    fn is_running(&self) -> bool {
+        !self.sessions().is_empty()
+    }
+
+    fn running_sessions(&self) -> Vec<String> {
+        self.sessions()
+    }
+
+    fn attach_session(&self, session: &str) -> Result<()> {
+        log::info!("[zellij] Attaching to session {}", session);
+        self.open_client(&["attach", session])
+    }
+
+    fn kill_session(&self, session: &str) -> Result<()> {
+        log::info!("[zellij] Deleting session {}", session);
+        // Deleting rather than killing, so the name is free for a new session
+        self.run(&["delete-session", "--force", session])?;
+        Ok(())
+    }
+
+    fn launch_workspace(&self, plan: &LaunchPlan) -> Result<()> {
+        let session = &plan.session_name;
+
+        log::info!(
+            "[zellij] Creating session {} for workspace: {}",
+            session,
+            plan.workspace_name
+        );
+        let path = write_layout_file(&format!("{}.kdl", session), &generate_kdl(plan))?;
+        self.open_client(&["--session", session, "--layout", &path.to_string_lossy()])?;
+
+        log::info!(
+            "[zellij] Workspace launched successfully in session {}",
+            session
+        );
+        Ok(())
+    }
+
+    fn open_terminal(&self) -> Result<()> {
+        self.open_client(&[])
+    }
+
+    fn run_command(&self, pane: &PanePlan, use_existing: bool) -> Result<()> {
+        // Add a pane to a running session instead of starting a new one
+        if use_existing {
+            if let Some(session) = self.sessions().first() {
+                let script = pane.startup_script();
+                self.run(&[
+                    "--session",
+                    session,
+                    "run",
+                    "--cwd",
+                    &pane.cwd,
+                    "--",
+                    "sh",
+                    "-c",
+                    &script,
+                ])?;
+                return Ok(());
+            }
+        }
+
+        let mut kdl = String::from("layout {\n");
+        write_pane(&mut kdl, pane, None, 1);
+        kdl.push_str("}\n");
+        let path = write_layout_file("cmdr-run.kdl", &kdl)?;
+        self.open_client(&["--layout", &path.to_string_lossy()])
+    }
+}
+
+/// Session names from `zellij list-sessions --no-formatting`
+fn parse_sessions(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.contains("EXITED"))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Generate a zellij KDL layout for a launch plan. zellij has no separate
+/// windows, so the tabs of every window become tabs of the one session.
+pub fn generate_kdl(plan: &LaunchPlan) -> String {
+    let mut kdl = String::from("layout {\n");
+
+    // Keep zellij's tab and status bars around every tab
+    kdl.push_str(
+        r#"    default_tab_template {
+        pane size=1 borderless=true {
+            plugin location="zellij:tab-bar"
+        }
+        children
+        pane size=2 borderless=true {
+            plugin location="zellij:status-bar"
+        }
+    }
+"#,
+    );
+
+    for tab in plan.windows.iter().flat_map(|w| w.tabs.iter()) {
+        match &tab.title {
+            Some(title) => kdl.push_str(&format!("    tab name={} {{\n", kdl_string(title))),
+            None => kdl.push_str("    tab {\n"),
+        }
+        write_node(&mut kdl, &tab.layout, None, 2);
+        kdl.push_str("    }\n");
+    }
+
+    kdl.push_str("}\n");
+    log::debug!("[zellij] Generated layout:\n{}", kdl);
+    kdl
+}
+
+fn write_node(kdl: &mut String, node: &SplitNode, size: Option<u32>, depth: usize) {
+    match node {
+        SplitNode::Pane(pane) => write_pane(kdl, pane, size, depth),
+        SplitNode::Split {
+            direction,
+            children,
+        } => {
+            // zellij names splits after the divider, like iTerm
+            let split = match direction {
+                SplitDirection::Horizontal => "vertical",
+                SplitDirection::Vertical => "horizontal",
+            };
+            kdl.push_str(&format!(
+                "{}pane{} split_direction=\"{}\" {{\n",
+                indent(depth),
+                size_attr(size),
+                split
+            ));
+            for child in children {
+                write_node(kdl, &child.node, Some(child.size), depth + 1);
+            }
+            kdl.push_str(&format!("{}}}\n", indent(depth)));
+        }
+    }
+}
+
+/// A pane starts in its directory; when it has variables or commands it
+/// runs them through `sh` and then hands over to the user's shell
+fn write_pane(kdl: &mut String, pane: &PanePlan, size: Option<u32>, depth: usize) {
+    kdl.push_str(&format!(
+        "{}pane{} cwd={}",
+        indent(depth),
+        size_attr(size),
+        kdl_string(&pane.cwd)
+    ));
+
+    if !pane.needs_script() {
+        kdl.push('\n');
+        return;
+    }
+
+    kdl.push_str(" command=\"sh\" {\n");
+    kdl.push_str(&format!(
+        "{}args \"-c\" {}\n",
+        indent(depth + 1),
+        kdl_string(&pane.startup_script())
+    ));
+    kdl.push_str(&format!("{}}}\n", indent(depth)));
+}
+
+fn size_attr(size: Option<u32>) -> String {
+    size.map(|s| format!(" size=\"{}%\"", s))
+        .unwrap_or_default()
+}
+
+fn indent(depth: usize) -> String {
+    "    ".repeat(depth)
+}
+
+/// Quote a string for KDL
+fn kdl_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{environment, pane, project, workspace};
+    use crate::config::{LayoutNode, Workspace, WorkspaceLayout, WorkspaceTab};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_sessions() {
+        let output = "cmdr-app-dev [Created 2m ago] (current)\n\
+                      cmdr-app-logs [Created 1h ago] (EXITED - attach to resurrect)\n\
+                      scratch [Created 5s ago]";
+        assert_eq!(parse_sessions(output), vec!["cmdr-app-dev", "scratch"]);
+        assert!(parse_sessions("").is_empty());
+    }
+
+    #[test]
+    fn test_kdl_string() {
+        assert_eq!(kdl_string("plain"), "\"plain\"");
+        assert_eq!(kdl_string("say \"hi\"\\n"), "\"say \\\"hi\\\"\\\\n\"");
+    }
+
+    #[test]
+    fn test_generate_kdl_golden() {
+        let mut api = pane((0, 1), "api", Some("npm run \"dev\""));
+        api.environment_variables = Some(HashMap::from([("PORT".to_string(), "4000".to_string())]));
+        let leaf = |pane: usize, size: Option<u32>| LayoutNode::Pane {
+            pane: Some(pane),
+            size,
+        };
+        let workspace = Workspace {
+            title: Some("servers".to_string()),
+            tabs: vec![WorkspaceTab {
+                title: Some("logs".to_string()),
+                window: 1,
+                layout: WorkspaceLayout {
+                    rows: 0,
+                    columns: Vec::new(),
+                    split: Some(LayoutNode::Split {
+                        direction: SplitDirection::Horizontal,
+                        size: None,
+                        children: vec![leaf(0, Some(70)), leaf(1, None)],
+                    }),
+                },
+                panes: vec![
+                    pane((0, 0), ".", Some("tail -f app.log")),
+                    pane((0, 0), ".", None),
+                ],
+            }],
+            ..workspace(vec![2, 1], vec![api, pane((1, 0), "/var/log", None)])
+        };
+        let environment = environment("dev", &[("STAGE", "it's dev")]);
+
+        let plan = LaunchPlan::build(&project("/work/app"), &workspace, Some(&environment));
+
+        let expected = r#"layout {
+    default_tab_template {
+        pane size=1 borderless=true {
+            plugin location="zellij:tab-bar"
+        }
+        children
+        pane size=2 borderless=true {
+            plugin location="zellij:status-bar"
+        }
+    }
+    tab name="servers" {
+        pane split_direction="horizontal" {
+            pane size="50%" split_direction="vertical" {
+                pane size="50%" cwd="/work/app" command="sh" {
+                    args "-c" "export STAGE='it'\\''s dev'; exec \"${SHELL:-sh}\""
+                }
+                pane size="50%" cwd="/work/app/api" command="sh" {
+                    args "-c" "export PORT='4000' STAGE='it'\\''s dev'; npm run \"dev\"; exec \"${SHELL:-sh}\""
+                }
+            }
+            pane size="50%" cwd="/var/log" command="sh" {
+                args "-c" "export STAGE='it'\\''s dev'; exec \"${SHELL:-sh}\""
+            }
+        }
+    }
+    tab name="logs" {
+        pane split_direction="vertical" {
+            pane size="70%" cwd="/work/app" command="sh" {
+                args "-c" "export STAGE='it'\\''s dev'; tail -f app.log; exec \"${SHELL:-sh}\""
+            }
+            pane size="30%" cwd="/work/app" command="sh" {
+                args "-c" "export STAGE='it'\\''s dev'; exec \"${SHELL:-sh}\""
+            }
+        }
+    }
+}
+"#;
+        assert_eq!(generate_kdl(&plan), expected);
+    }
+
+    #[test]
+    fn test_plain_pane_has_no_command() {
+        let workspace = workspace(vec![1], Vec::new());
+
+        let kdl = generate_kdl(&LaunchPlan::build(&project("/work/app"), &workspace, None));
+        assert!(kdl.contains("    tab {\n        pane cwd=\"/work/app\"\n    }\n"));
+    }
+}
@@ -1,12 +1,14 @@
 import { defineStore } from "pinia";
 import { ref, computed } from "vue";
-import type { Project } from "@/types";
+import type { Command, ExistingSession, Project, RunningWorkspace, SyncReport } from "@/types";
 import { invoke } from "@tauri-apps/api/core";
 
 export const useProjectsStore = defineStore("projects", () => {
   const projects = ref<Project[]>([]);
   const loading = ref(false);
   const error = ref<string | null>(null);
+  // A launch waiting for the user to decide what to do with the running session
+  const pendingLaunch = ref<RunningWorkspace | null>(null);
 
   const recentProjects = computed(() => {
     return [...projects.value]
@@ -97,10 +99,104 @@ export const useProjectsStore = defineStore("projects", () => {
     }
   }
 
+  /**
+   * Launch a workspace. When its session is already running and no choice
+   * was made, the launch is held in `pendingLaunch` and false is returned.
+   */
+  async function launchWorkspace(
+    projectId: string,
+    workspaceId: string,
+    existing?: ExistingSession
+  ) {
+    if (!existing) {
+      const running = await invoke<RunningWorkspace[]>("list_running_workspaces");
+      const session = running.find(
+        (r) => r.projectId === projectId && r.workspaceId === workspaceId
+      );
+      if (session) {
+        console.log("[ProjectsStore] Workspace already running:", session.sessionName);
+        pendingLaunch.value = session;
+        return false;
+      }
+    }
+
+    console.log("[ProjectsStore] Launching workspace:", workspaceId, "existing:", existing);
+    await invoke("launch_workspace", { projectId, workspaceId, existing });
+    return true;
+  }
+
+  async function stopWorkspace(projectId: string, workspaceId: string) {
+    console.log("[ProjectsStore] Stopping workspace:", workspaceId);
+    await invoke("stop_workspace", { projectId, workspaceId });
+    console.log("[ProjectsStore] Workspace stopped");
+  }
+
+  async function listTmuxSessions() {
+    return invoke<string[]>("list_tmux_sessions");
+  }
+
+  /** Save a running tmux session as a new workspace of a project */
+  async function importTmuxSession(projectId: string, session: string, name?: string) {
+    console.log("[ProjectsStore] Importing tmux session:", session, "into project:", projectId);
+    try {
+      const updated = await invoke<Project>("import_tmux_session", { projectId, session, name });
+      const index = projects.value.findIndex((p) => p.id === projectId);
+      if (index !== -1) {
+        projects.value[index] = updated;
+      }
+      console.log("[ProjectsStore] tmux session imported");
+      return updated;
+    } catch (e) {
+      error.value = String(e);
+      console.error("[ProjectsStore] Failed to import tmux session:", e);
+      throw e;
+    }
+  }
+
+  /** Commands found in the Procfile, package.json, Makefile, justfile or cargo aliases of a directory */
+  async function discoverCommands(path: string) {
+    return invoke<Command[]>("discover_commands", { path });
+  }
+
+  /** Re-import a project's commands from its repository */
+  async function syncCommands(id: string) {
+    console.log("[ProjectsStore] Syncing imported commands of project:", id);
+    try {
+      const { project, report } = await invoke<{ project: Project; report: SyncReport }>(
+        "sync_project_commands",
+        { id }
+      );
+      const index = projects.value.findIndex((p) => p.id === id);
+      if (index !== -1) {
+        projects.value[index] = project;
+      }
+      console.log("[ProjectsStore] Commands synced:", report);
+      return report;
+    } catch (e) {
+      error.value = String(e);
+      console.error("[ProjectsStore] Failed to sync commands:", e);
+      throw e;
+    }
+  }
+
+  async function resolvePendingLaunch(existing: ExistingSession) {
+    const session = pendingLaunch.value;
+    pendingLaunch.value = null;
+    if (!session || existing === "cancel") return;
+
+    try {
+      await launchWorkspace(session.projectId, session.workspaceId, existing);
+    } catch (e) {
+      console.error("[ProjectsStore] Failed to launch workspace:", e);
+      alert("Failed to launch workspace: " + e);
+    }
+  }
+
   return {
     projects,
     loading,
     error,
+    pendingLaunch,
     recentProjects,
     projectById,
     loadProjects,
@@ -108,5 +204,12 @@ export const useProjectsStore = defineStore("projects", () => {
     updateProject,
     deleteProject,
     openProject,
+    launchWorkspace,
+    stopWorkspace,
+    listTmuxSessions,
+    importTmuxSession,
+    discoverCommands,
+    syncCommands,
+    resolvePendingLaunch,
   };
 });
@@ -0,0 +1,196 @@
+import { defineStore } from "pinia";
+import { ref } from "vue";
+import type {
+  HistoryFilter,
+  PipelineInfo,
+  RunExit,
+  RunInfo,
+  RunOutput,
+  RunRecord,
+  RunStatus,
+} from "@/types";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+/** A command running (or finished) inside cmdr, with its output so far */
+export interface Run extends RunInfo {
+  output: string;
+  running: boolean;
+  exitCode?: number | null;
+  status?: RunStatus;
+}
+
+export const useRunsStore = defineStore("runs", () => {
+  const runs = ref<Run[]>([]);
+  const activeRunId = ref<string | null>(null);
+  /** Finished runs, newest first, as last loaded */
+  const history = ref<RunRecord[]>([]);
+  /** Pipelines started in this window, or running when it was loaded */
+  const pipelines = ref<PipelineInfo[]>([]);
+  let listening = false;
+
+  function runById(runId: string) {
+    return runs.value.find((r) => r.runId === runId);
+  }
+
+  function addRun(info: RunInfo) {
+    if (!runById(info.runId)) {
+      runs.value.push({ ...info, output: "", running: true });
+    }
+  }
+
+  function updatePipeline(info: PipelineInfo) {
+    const index = pipelines.value.findIndex((p) => p.pipelineId === info.pipelineId);
+    if (index === -1) {
+      pipelines.value.push(info);
+    } else {
+      pipelines.value[index] = info;
+    }
+  }
+
+  /** Subscribe to run events and pick up runs started before a reload */
+  async function init() {
+    if (listening) return;
+    listening = true;
+
+    // Pipeline steps start without a call from this window
+    await listen<RunInfo>("run-start", (event) => addRun(event.payload));
+    await listen<PipelineInfo>("pipeline-update", (event) => {
+      updatePipeline(event.payload);
+      if (event.payload.status !== "running") {
+        console.log("[RunsStore] Pipeline finished:", event.payload.name, "status:", event.payload.status);
+      }
+    });
+    await listen<RunOutput>("run-output", (event) => {
+      const run = runById(event.payload.runId);
+      if (run) run.output += event.payload.data;
+    });
+    await listen<RunExit>("run-exit", (event) => {
+      const run = runById(event.payload.runId);
+      if (!run) return;
+      run.running = false;
+      run.exitCode = event.payload.exitCode;
+      run.status = event.payload.status;
+      console.log("[RunsStore] Run exited:", run.command, "status:", run.status);
+      loadHistory().catch((e) => console.error("[RunsStore] Failed to reload history:", e));
+    });
+
+    const live = await invoke<RunInfo[]>("list_runs");
+    live.forEach(addRun);
+    const livePipelines = await invoke<PipelineInfo[]>("list_pipelines");
+    livePipelines.forEach(updatePipeline);
+  }
+
+  async function startRun(
+    projectId: string,
+    command: string,
+    options: {
+      commandId?: string;
+      environment?: string;
+      values?: Record<string, string>;
+      cols?: number;
+      rows?: number;
+    } = {}
+  ) {
+    await init();
+    console.log("[RunsStore] Starting run:", command, "for project:", projectId);
+    const info = await invoke<RunInfo>("start_run", { projectId, command, ...options });
+    addRun(info);
+    activeRunId.value = info.runId;
+    return info;
+  }
+
+  /** Run a saved command after everything it depends on */
+  async function startPipeline(
+    projectId: string,
+    commandId: string,
+    options: {
+      environment?: string;
+      values?: Record<string, string>;
+      parallel?: boolean;
+      cols?: number;
+      rows?: number;
+    } = {}
+  ) {
+    await init();
+    console.log("[RunsStore] Starting pipeline:", commandId, "for project:", projectId);
+    const info = await invoke<PipelineInfo>("start_pipeline", { projectId, commandId, ...options });
+    // Progress events may already have arrived
+    if (!pipelines.value.some((p) => p.pipelineId === info.pipelineId)) {
+      pipelines.value.push(info);
+    }
+    return info;
+  }
+
+  async function cancelPipeline(pipelineId: string) {
+    console.log("[RunsStore] Cancelling pipeline:", pipelineId);
+    await invoke("cancel_pipeline", { pipelineId });
+  }
+
+  /** Forget a finished pipeline; its runs stay until they are closed */
+  function dismissPipeline(pipelineId: string) {
+    pipelines.value = pipelines.value.filter(
+      (p) => p.pipelineId !== pipelineId || p.status === "running"
+    );
+  }
+
+  async function writeRun(runId: string, data: string) {
+    await invoke("write_run", { runId, data });
+  }
+
+  async function resizeRun(runId: string, cols: number, rows: number) {
+    const run = runById(runId);
+    if (!run?.running) return;
+    await invoke("resize_run", { runId, cols, rows });
+  }
+
+  async function killRun(runId: string) {
+    console.log("[RunsStore] Killing run:", runId);
+    await invoke("kill_run", { runId });
+  }
+
+  /** Load finished runs of every project, newest first */
+  async function loadHistory(filter: HistoryFilter = { limit: 200 }) {
+    history.value = await invoke<RunRecord[]>("get_run_history", { filter });
+    return history.value;
+  }
+
+  /** The newest finished run of a command, from the loaded history */
+  function lastRun(projectId: string, commandId: string) {
+    return history.value.find((r) => r.projectId === projectId && r.commandId === commandId);
+  }
+
+  async function clearHistory(projectId?: string, commandId?: string) {
+    console.log("[RunsStore] Clearing run history:", projectId ?? "all projects", commandId ?? "");
+    await invoke("clear_run_history", { projectId, commandId });
+    await loadHistory();
+  }
+
+  /** Forget a finished run and its output */
+  function dismissRun(runId: string) {
+    runs.value = runs.value.filter((r) => r.runId !== runId || r.running);
+    if (activeRunId.value === runId) {
+      activeRunId.value = runs.value[runs.value.length - 1]?.runId ?? null;
+    }
+  }
+
+  return {
+    runs,
+    activeRunId,
+    history,
+    pipelines,
+    runById,
+    init,
+    startRun,
+    startPipeline,
+    cancelPipeline,
+    dismissPipeline,
+    writeRun,
+    resizeRun,
+    killRun,
+    dismissRun,
+    loadHistory,
+    lastRun,
+    clearHistory,
+  };
+});
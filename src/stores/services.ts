@@ -0,0 +1,94 @@
+import { defineStore } from "pinia";
+import { ref } from "vue";
+import type { ServiceInfo, ServiceLog } from "@/types";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+/** Log lines kept per project, like the supervisor does */
+const MAX_LOG_LINES = 1000;
+
+export const useServicesStore = defineStore("services", () => {
+  const services = ref<ServiceInfo[]>([]);
+  /** Recent service output by project id */
+  const logs = ref<Record<string, ServiceLog[]>>({});
+  let listening = false;
+
+  function updateService(info: ServiceInfo) {
+    const index = services.value.findIndex(
+      (s) => s.projectId === info.projectId && s.commandId === info.commandId
+    );
+    if (index === -1) {
+      services.value.push(info);
+    } else {
+      services.value[index] = info;
+    }
+  }
+
+  /** Subscribe to supervisor events and pick up services already started */
+  async function init() {
+    if (listening) return;
+    listening = true;
+
+    await listen<ServiceInfo>("service-state", (event) => updateService(event.payload));
+    await listen<ServiceLog>("service-log", (event) => {
+      const lines = (logs.value[event.payload.projectId] ??= []);
+      lines.push(event.payload);
+      if (lines.length > MAX_LOG_LINES) lines.splice(0, lines.length - MAX_LOG_LINES);
+    });
+
+    const live = await invoke<ServiceInfo[]>("list_services", {});
+    live.forEach(updateService);
+  }
+
+  /** Load a project's recent output, after a reload */
+  async function loadLogs(projectId: string) {
+    await init();
+    logs.value[projectId] = await invoke<ServiceLog[]>("get_service_logs", { projectId });
+  }
+
+  function projectServices(projectId: string) {
+    return services.value.filter((s) => s.projectId === projectId);
+  }
+
+  function service(projectId: string, commandId: string) {
+    return services.value.find((s) => s.projectId === projectId && s.commandId === commandId);
+  }
+
+  async function startServices(projectId: string, environment?: string) {
+    await init();
+    console.log("[ServicesStore] Starting services of project:", projectId);
+    const started = await invoke<ServiceInfo[]>("start_services", { projectId, environment });
+    // State events may already have arrived
+    started.forEach((info) => {
+      if (!service(info.projectId, info.commandId)) updateService(info);
+    });
+  }
+
+  async function stopServices(projectId: string) {
+    console.log("[ServicesStore] Stopping services of project:", projectId);
+    await invoke("stop_services", { projectId });
+  }
+
+  async function restartService(projectId: string, commandId: string, environment?: string) {
+    await init();
+    console.log("[ServicesStore] Restarting service:", commandId);
+    await invoke("restart_service", { projectId, commandId, environment });
+  }
+
+  function clearLogs(projectId: string) {
+    logs.value[projectId] = [];
+  }
+
+  return {
+    services,
+    logs,
+    init,
+    loadLogs,
+    projectServices,
+    service,
+    startServices,
+    stopServices,
+    restartService,
+    clearLogs,
+  };
+});
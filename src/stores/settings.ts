@@ -8,6 +8,7 @@ export const useSettingsStore = defineStore("settings", () => {
     defaultTerminal: "iterm2",
     theme: "dark",
     terminalBehavior: "new_window",
+    fallbackTerminals: [],
     globalCommands: [],
   });
   const loading = ref(false);
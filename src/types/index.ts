@@ -11,9 +11,14 @@ export interface Project {
   createdAt: string;
 }
 
+export type ConfigLayer = "user" | "project";
+
 export interface Environment {
   name: string;
   variables: Record<string, string>;
+  layer?: ConfigLayer;
+  /** Variables inherited from the shared environment with the same key */
+  sharedVariables?: string[];
 }
 
 export interface Workspace {
@@ -21,13 +26,34 @@ export interface Workspace {
   name: string;
   layout: WorkspaceLayout;
   panes: Pane[];
+  defaultEnvironment?: string;
+  title?: string;
+  tabs?: WorkspaceTab[];
+  layer?: ConfigLayer;
+}
+
+export interface WorkspaceTab {
+  title?: string;
+  window: number;
+  layout: WorkspaceLayout;
+  panes: Pane[];
 }
 
 export interface WorkspaceLayout {
   rows: number;
   columns: number[];
+  split?: LayoutNode;
 }
 
+export type LayoutNode =
+  | {
+      type: "split";
+      direction: "horizontal" | "vertical";
+      size?: number;
+      children: LayoutNode[];
+    }
+  | { type: "pane"; pane?: number; size?: number };
+
 export interface Pane {
   position: [number, number];
   directory: string;
@@ -41,12 +67,236 @@ export interface Command {
   command: string;
   description?: string;
   icon?: string;
+  parameters?: CommandParameter[];
+  /** Ids of commands that must succeed before this one runs */
+  dependsOn?: string[];
+  /** Set on long-running commands cmdr supervises as services */
+  service?: ServiceOptions;
+  /** Set on commands imported from a file in the project's repository */
+  source?: CommandSource;
+  layer?: ConfigLayer;
+}
+
+export type SourceKind = "procfile" | "packageJson" | "makefile" | "justfile" | "cargoAlias";
+
+export interface CommandSource {
+  kind: SourceKind;
+  /** Name of the process, script, target, recipe or alias */
+  name: string;
+  /** The command as last imported; edited commands are left alone by syncing */
+  imported: ImportedCommand;
+}
+
+/** The fields of a command as they were imported */
+export interface ImportedCommand {
+  name: string;
+  command: string;
+  description?: string;
+  parameters?: CommandParameter[];
+}
+
+/** What syncing a project's imported commands changed, by command name */
+export interface SyncReport {
+  added: string[];
+  updated: string[];
+  removed: string[];
+  /** Imported commands that were edited since, left as they are */
+  kept: string[];
+}
+
+export type RestartPolicy = "never" | "onFailure";
+
+export interface ServiceOptions {
+  restart: RestartPolicy;
+  maxRestarts: number;
+  environment?: string;
+}
+
+export type ParameterType = "string" | "number" | "boolean" | "choice";
+
+export interface CommandParameter {
+  name: string;
+  type: ParameterType;
+  default?: string;
+  choices?: string[];
+  required: boolean;
 }
 
 export interface AppSettings {
-  defaultTerminal: "iterm2" | "terminal" | "windows_terminal" | "gnome";
+  defaultTerminal: "iterm2" | "terminal" | "tmux" | "zellij" | "kitty" | "wezterm" | "windows_terminal" | "gnome" | "konsole" | "tilix";
   theme: "dark" | "light";
   defaultProjectsPath?: string;
   terminalBehavior: "new_window" | "use_existing";
+  fallbackTerminals: string[];
   globalCommands: Command[];
 }
+
+export type ExistingSession = "attach" | "recreate" | "cancel";
+
+export interface RunningWorkspace {
+  projectId: string;
+  projectName: string;
+  workspaceId: string;
+  workspaceName: string;
+  sessionName: string;
+  terminal: string;
+}
+
+export interface TerminalInfo {
+  id: string;
+  name: string;
+  available: boolean;
+  running: boolean;
+}
+
+export interface LaunchPlan {
+  projectId: string;
+  workspaceId: string;
+  workspaceName: string;
+  sessionName: string;
+  environment?: string;
+  windows: { tabs: TabPlan[] }[];
+}
+
+export interface TabPlan {
+  title?: string;
+  layout: SplitNode;
+}
+
+export type SplitNode =
+  | ({ type: "pane" } & PanePlan)
+  | {
+      type: "split";
+      direction: "horizontal" | "vertical";
+      children: { size: number; node: SplitNode }[];
+    };
+
+export interface PanePlan {
+  position?: [number, number];
+  cwd: string;
+  env: Record<string, string>;
+  commands: string[];
+}
+
+export interface ValidationIssue {
+  workspaceId: string;
+  workspaceName: string;
+  tab?: number;
+  pane?: number;
+  rule:
+    | "row_count_mismatch"
+    | "empty_row"
+    | "pane_out_of_bounds"
+    | "duplicate_position"
+    | "empty_split"
+    | "invalid_size"
+    | "unknown_pane"
+    | "pane_not_in_layout";
+  message: string;
+}
+
+export interface RunInfo {
+  runId: string;
+  projectId: string;
+  commandId?: string | null;
+  command: string;
+  environment?: string | null;
+  pipelineId?: string | null;
+  cwd: string;
+  pid?: number;
+  startedAt: string;
+}
+
+export interface RunOutput {
+  runId: string;
+  data: string;
+}
+
+export interface RunExit {
+  runId: string;
+  exitCode?: number | null;
+  status: RunStatus;
+}
+
+export type RunStatus = "succeeded" | "failed" | "killed";
+
+export interface RunRecord {
+  runId: string;
+  projectId: string;
+  commandId?: string | null;
+  command: string;
+  environment?: string | null;
+  cwd: string;
+  startedAt: string;
+  finishedAt: string;
+  durationMs: number;
+  exitCode?: number | null;
+  status: RunStatus;
+  output: string;
+  outputTruncated: boolean;
+}
+
+export interface HistoryFilter {
+  commandId?: string;
+  status?: RunStatus;
+  since?: string;
+  limit?: number;
+}
+
+export type StepStatus = "pending" | "running" | "succeeded" | "failed" | "killed" | "skipped";
+
+export type PipelineStatus = "running" | "succeeded" | "failed" | "cancelled";
+
+export interface StepInfo {
+  commandId: string;
+  name: string;
+  command: string;
+  /** Indices of the steps that must succeed first */
+  dependsOn: number[];
+  status: StepStatus;
+  runId?: string | null;
+}
+
+export interface PipelineInfo {
+  pipelineId: string;
+  projectId: string;
+  commandId: string;
+  name: string;
+  environment?: string | null;
+  parallel: boolean;
+  status: PipelineStatus;
+  steps: StepInfo[];
+  startedAt: string;
+}
+
+export type ServiceState =
+  | "starting"
+  | "running"
+  | "stopping"
+  | "backoff"
+  | "exited"
+  | "failed"
+  | "stopped";
+
+export interface ServiceInfo {
+  projectId: string;
+  commandId: string;
+  name: string;
+  command: string;
+  color: string;
+  state: ServiceState;
+  pid?: number | null;
+  restarts: number;
+  exitCode?: number | null;
+  startedAt?: string | null;
+  retryInMs?: number | null;
+}
+
+export interface ServiceLog {
+  projectId: string;
+  commandId: string;
+  name: string;
+  color: string;
+  line: string;
+  text: string;
+}
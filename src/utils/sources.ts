@@ -0,0 +1,10 @@
+import type { SourceKind } from "@/types";
+
+/** The file an imported command comes from */
+export const SOURCE_FILES: Record<SourceKind, string> = {
+  procfile: "Procfile",
+  packageJson: "package.json",
+  makefile: "Makefile",
+  justfile: "justfile",
+  cargoAlias: ".cargo/config.toml",
+};